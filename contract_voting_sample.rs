@@ -1,15 +1,30 @@
 use crate::coin_helpers::validate_sent_sufficient_coin;
 use crate::error::ContractError;
 use crate::msg::{
-    CreatePollResponse, ExecuteMsg, InstantiateMsg, PollResponse, QueryMsg, TokenStakeResponse,
+    AcceptedDenomsResponse, ActivePollIdsResponse, ActiveVotingWeightResponse, CanWithdrawResponse,
+    ClosestToQuorumResponse, CreatePollResponse, CreatorRecordResponse, Cw20HookMsg, ExecuteMsg,
+    FreeVotingPowerResponse, FullWithdrawHeightResponse, InstantiateMsg, MigrateMsg,
+    OpenLockCountResponse, ParticipationRateResponse, PauseStatusResponse, PollResponse,
+    PollResult, PollScheduleResponse, PollSummariesResponse, PollSummary, PollWithMyVoteResponse,
+    PollsResponse, QueryMsg, QuorumDenominatorResponse, StakerInfo, StaleEntriesResponse,
+    SubPollsResponse, TemplateResponse, TemplatesResponse, TokenStakeResponse, TopStakersResponse,
+    VoteValidityResponse, VoterAlignmentResponse, VoterResponse, VotesInRangeResponse,
+    VotingOpenResponse, WeightBucket, WeightDistributionResponse, WeightStatsResponse,
 };
 use crate::state::{
-    bank, bank_read, config, config_read, poll, poll_read, Poll, PollStatus, State, Voter,
+    active_poll, active_poll_read, bank, bank_read, config, config_read, legacy_bank_read,
+    legacy_config_read, legacy_poll_read, poll, poll_read, template, template_read, AutoExtend,
+    CreatePollParams, Poll, PollStatus, RejectReason, State, TokenManager, VoteOption, Voter,
 };
 use cosmwasm_std::{
-    attr, coin, entry_point, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Env,
-    MessageInfo, Response, StdError, StdResult, Storage, Uint128,
+    attr, coin, entry_point, from_binary, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps,
+    DepsMut, Env, MessageInfo, Order, Response, StdError, StdResult, Storage, Uint128, WasmMsg,
 };
+use cw2::{get_contract_version, set_contract_version};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use sha2::{Digest, Sha256};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 
 /*
     a. Concepts in the code: 
@@ -32,15 +47,23 @@ use cosmwasm_std::{
 
 */
  
-/* Constants defined here for type of minimum staking amount, 
-    minimum description length, maximum description length.
+/* Constants defined here for type of default minimum staking amount, 
+    default minimum and maximum description length.
     Can check the validity of the description based on its length.
 */
+const CONTRACT_NAME: &str = "crates.io:cw-voting";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const VOTING_TOKEN: &str = "voting_token";
 pub const DEFAULT_END_HEIGHT_BLOCKS: &u64 = &100_800_u64;
-const MIN_STAKE_AMOUNT: u128 = 1;
-const MIN_DESC_LENGTH: u64 = 3;
-const MAX_DESC_LENGTH: u64 = 64;
+const REVEAL_PERIOD_BLOCKS: u64 = 14_400;
+const DEFAULT_MIN_STAKE_AMOUNT: u128 = 1;
+const DEFAULT_MIN_DESC_LENGTH: u64 = 3;
+const DEFAULT_MAX_DESC_LENGTH: u64 = 64;
+const MAX_COMMENT_LENGTH: u64 = 256;
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+const TOP_STAKERS_MAX_LIMIT: u32 = 50;
+const WEIGHT_STATS_MAX_VOTERS: usize = 1000;
 
 #[entry_point]
 pub fn instantiate(
@@ -49,6 +72,19 @@ pub fn instantiate(
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
+    if msg.weight_unit == Some(Uint128::zero()) {
+        return Err(ContractError::InvalidWeightUnit {});
+    }
+    validate_denom(&msg.denom)?;
+    let min_desc_length = msg.min_desc_length.unwrap_or(DEFAULT_MIN_DESC_LENGTH);
+    let max_desc_length = msg.max_desc_length.unwrap_or(DEFAULT_MAX_DESC_LENGTH);
+    validate_desc_lengths(min_desc_length, max_desc_length)?;
+    // without a cw20 token the contract stakes native coins of the denom
+    let cw20_token = match msg.cw20_token {
+        Some(cw20_token) => Some(deps.api.addr_validate(cw20_token.as_str())?),
+        None => None,
+    };
+
     /* state contains the denom of token to stake, owner,
      count of polls & staked tokens which are initially zero */
     let state = State {
@@ -56,9 +92,25 @@ pub fn instantiate(
         owner: info.sender,
         poll_count: 0,
         staked_tokens: Uint128::zero(),
+        creation_paused: false,
+        reject_duplicate_descriptions: msg.reject_duplicate_descriptions.unwrap_or(false),
+        active_voting_weight: Uint128::zero(),
+        max_absolute_end_height: msg.max_absolute_end_height,
+        default_poll_duration: *DEFAULT_END_HEIGHT_BLOCKS,
+        weight_unit: msg.weight_unit,
+        min_stake_amount: msg
+            .min_stake_amount
+            .unwrap_or_else(|| Uint128::from(DEFAULT_MIN_STAKE_AMOUNT)),
+        min_desc_length,
+        max_desc_length,
+        pending_owner: None,
+        unbonding_period: msg.unbonding_period,
+        pause_reason: None,
+        cw20_token,
     };
 
     config(deps.storage).save(&state)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     Ok(Response::default())
 }
@@ -73,27 +125,63 @@ pub fn execute(
     /* Different types of ExecuteMsg messages defined below.
         By default all msgs have (deps, env, info) as default args
         StakeVotingTokens:
-        WithdrawVotingTokens: also specify the amount
-        CastVote: also specify the poll_id, weight, and 
+        Receive: cw20 hook, stakes the received amount when the contract stakes a cw20 token
+        WithdrawVotingTokens: also specify the amount, only without an unbonding period
+        Unbond: also specify the amount, released after the unbonding period
+        ClaimUnbonded: sends the unbonded amounts whose unbonding period is over
+        CastVote: also specify the poll_id, weight, vote and an optional comment
+        CommitVote: commit-reveal polls only, specify the poll_id and the hash of the vote
+        RevealVote: after the voting window, specify the poll_id, vote, weight and salt
+            behind the commitment
         EndPoll:also specify the poll_id
-        CreatePoll: specify the quorum percentage, description, start/end height
+        CancelPoll: creator only, specify the poll_id of a poll which is still in progress
+        CreatePoll: specify the quorum percentage, description, start/end height and
+            optionally the parent poll this one amends and how it is extended when nearly at quorum
+        SetCreationPaused: owner only, specify whether poll creation is paused and optionally why
+        TransferOwnership: owner only, specify the address which may accept the ownership
+        AcceptOwnership: pending owner only, completes the transfer
+        SaveTemplate: owner only, specify a name and the poll parameters to reuse
+        CreateFromTemplate: specify the template name and the description of the new poll
+        TransferStake: specify the recipient and amount of free stake to move
+        MergeStake: owner only, specify the old and new address of a migrated staker
+        UpdateConfig: owner only, specify any of the parameters to change,
+            clear_max_absolute_end_height removes the end height cap
+        ReserveForPoll: specify the poll_id of a poll which hasn't opened yet and the weight to lock
+        ActivateReservation: specify the poll_id and the vote to cast with the reserved weight
     */
     match msg {
         ExecuteMsg::StakeVotingTokens {} => stake_voting_tokens(deps, env, info),
+        ExecuteMsg::Receive(wrapper) => receive_cw20(deps, env, info, wrapper),
         ExecuteMsg::WithdrawVotingTokens { amount } => {
             withdraw_voting_tokens(deps, env, info, amount)
         }
+        ExecuteMsg::Unbond { amount } => unbond(deps, env, info, amount),
+        ExecuteMsg::ClaimUnbonded {} => claim_unbonded(deps, env, info),
         ExecuteMsg::CastVote {
             poll_id,
             vote,
             weight,
-        } => cast_vote(deps, env, info, poll_id, vote, weight),
+            comment,
+        } => cast_vote(deps, env, info, poll_id, vote, weight, comment),
+        ExecuteMsg::ChangeVote {
+            poll_id,
+            vote,
+            weight,
+        } => change_vote(deps, env, info, poll_id, vote, weight),
         ExecuteMsg::EndPoll { poll_id } => end_poll(deps, env, info, poll_id),
+        ExecuteMsg::CancelPoll { poll_id } => cancel_poll(deps, env, info, poll_id),
         ExecuteMsg::CreatePoll {
             quorum_percentage,
             description,
             start_height,
             end_height,
+            parent_poll_id,
+            requires_poll,
+            max_self_stake_percentage,
+            commit_reveal,
+            max_total_weight,
+            auto_extend,
+            min_vote_percentage,
         } => create_poll(
             deps,
             env,
@@ -102,7 +190,65 @@ pub fn execute(
             description,
             start_height,
             end_height,
+            parent_poll_id,
+            requires_poll,
+            max_self_stake_percentage,
+            commit_reveal.unwrap_or(false),
+            max_total_weight,
+            auto_extend,
+            min_vote_percentage,
+        ),
+        ExecuteMsg::SetCreationPaused { paused, reason } => {
+            set_creation_paused(deps, env, info, paused, reason)
+        }
+        ExecuteMsg::TransferOwnership { new_owner } => {
+            transfer_ownership(deps, env, info, new_owner)
+        }
+        ExecuteMsg::AcceptOwnership {} => accept_ownership(deps, env, info),
+        ExecuteMsg::SaveTemplate { name, params } => save_template(deps, env, info, name, params),
+        ExecuteMsg::CreateFromTemplate { name, description } => {
+            create_from_template(deps, env, info, name, description)
+        }
+        ExecuteMsg::TransferStake { to, amount } => transfer_stake(deps, env, info, to, amount),
+        ExecuteMsg::MergeStake { from, to } => merge_stake(deps, env, info, from, to),
+        ExecuteMsg::UpdateConfig {
+            default_poll_duration,
+            reject_duplicate_descriptions,
+            max_absolute_end_height,
+            clear_max_absolute_end_height,
+            denom,
+            min_stake_amount,
+            min_desc_length,
+            max_desc_length,
+        } => update_config(
+            deps,
+            env,
+            info,
+            default_poll_duration,
+            reject_duplicate_descriptions,
+            max_absolute_end_height,
+            clear_max_absolute_end_height,
+            denom,
+            min_stake_amount,
+            min_desc_length,
+            max_desc_length,
         ),
+        ExecuteMsg::CommitVote {
+            poll_id,
+            commitment,
+        } => commit_vote(deps, env, info, poll_id, commitment),
+        ExecuteMsg::RevealVote {
+            poll_id,
+            vote,
+            weight,
+            salt,
+        } => reveal_vote(deps, env, info, poll_id, vote, weight, salt),
+        ExecuteMsg::ReserveForPoll { poll_id, weight } => {
+            reserve_for_poll(deps, env, info, poll_id, weight)
+        }
+        ExecuteMsg::ActivateReservation { poll_id, vote } => {
+            activate_reservation(deps, env, info, poll_id, vote)
+        }
     }
 }
 
@@ -111,36 +257,78 @@ pub fn stake_voting_tokens(
     _env: Env,
     info: MessageInfo,
 ) -> Result<Response, ContractError> {
-    let key = info.sender.as_str().as_bytes();
-
-    // token manager and state is mutable
-
-    let mut token_manager = bank_read(deps.storage).may_load(key)?.unwrap_or_default();
-
-    let mut state = config(deps.storage).load()?;
+    let state = config_read(deps.storage).load()?;
+    if state.cw20_token.is_some() {
+        return Err(ContractError::UnsupportedToken {});
+    }
 
     // validate sufficient coin sent from funds, check that given sent coin matches expected denom,
     // and also is greater than or equal to required_amount.  Return Result<(), ContractError>, 
     // only returns an error 
-    validate_sent_sufficient_coin(&info.funds, Some(coin(MIN_STAKE_AMOUNT, &state.denom)))?;
-    let funds = info
-        .funds
-        .iter()
-        .find(|coin| coin.denom.eq(&state.denom))
-        .unwrap();
+    validate_sent_sufficient_coin(
+        &info.funds,
+        Some(coin(state.min_stake_amount.u128(), &state.denom)),
+    )?;
+    // with a zero minimum the check above passes without any coin of the denom
+    let funds = match info.funds.iter().find(|coin| coin.denom.eq(&state.denom)) {
+        Some(funds) => funds,
+        None => return Err(ContractError::InsufficientFundsSent {}),
+    };
+
+    add_stake(deps.storage, &info.sender, funds.amount)
+}
+
+/*
+ * cw20 hook: the token contract calls this after tokens were sent to this contract. Only
+ * the configured cw20 token is accepted, and the amount is staked for the original sender.
+ */
+pub fn receive_cw20(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    wrapper: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let state = config_read(deps.storage).load()?;
+    if state.cw20_token.as_ref() != Some(&info.sender) {
+        return Err(ContractError::UnsupportedToken {});
+    }
+
+    match from_binary(&wrapper.msg)? {
+        Cw20HookMsg::StakeVotingTokens {} => {
+            if wrapper.amount < state.min_stake_amount {
+                return Err(ContractError::InsufficientFundsSent {});
+            }
+            let staker = deps.api.addr_validate(wrapper.sender.as_str())?;
+            add_stake(deps.storage, &staker, wrapper.amount)
+        }
+    }
+}
+
+// adds the amount to the staker's balance and to the total staked tokens
+fn add_stake(
+    storage: &mut dyn Storage,
+    staker: &Addr,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let key = staker.as_str().as_bytes();
+
+    // token manager and state is mutable
+
+    let mut token_manager = bank_read(storage).may_load(key)?.unwrap_or_default();
+
+    let mut state = config(storage).load()?;
 
     // token manager will add the amount in funds; 
     // this is done after validating sufficient coin sent above, but maybe a better way to do this
-    token_manager.token_balance += funds.amount;
+    token_manager.token_balance = token_manager.token_balance.checked_add(amount)?;
 
     // update total number of staked tokens, add the state's staked tokens with the funds' amount 
-    let staked_tokens = state.staked_tokens.u128() + funds.amount.u128();
-    state.staked_tokens = Uint128::from(staked_tokens);
+    state.staked_tokens = state.staked_tokens.checked_add(amount)?;
 
     // save the different updates to config and bank state below
-    config(deps.storage).save(&state)?;
+    config(storage).save(&state)?;
 
-    bank(deps.storage).save(key, &token_manager)?;
+    bank(storage).save(key, &token_manager)?;
 
     Ok(Response::default())
 }
@@ -148,379 +336,6142 @@ pub fn stake_voting_tokens(
 // Withdraw amount if not staked. By default all funds will be withdrawn.
 pub fn withdraw_voting_tokens(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     amount: Option<Uint128>,
 ) -> Result<Response, ContractError> {
     let sender_address_raw = info.sender.as_str().as_bytes();
 
+    // with an unbonding period tokens can only leave through Unbond and ClaimUnbonded
+    if let Some(unbonding_period) = config_read(deps.storage).load()?.unbonding_period {
+        return Err(ContractError::UnbondingRequired { unbonding_period });
+    }
+
     if let Some(mut token_manager) = bank_read(deps.storage).may_load(sender_address_raw)? {
-        let largest_staked = locked_amount(&sender_address_raw, deps.storage);
+        // tokens locked in any open poll stay staked
+        let locked = open_locked_sum(deps.storage, &token_manager, env.block.height)?;
         let withdraw_amount = amount.unwrap_or(token_manager.token_balance);
-        if largest_staked + withdraw_amount > token_manager.token_balance {
-            let max_amount = token_manager.token_balance.checked_sub(largest_staked)?;
+        if locked + withdraw_amount > token_manager.token_balance {
+            let max_amount = token_manager.token_balance.saturating_sub(locked);
             Err(ContractError::ExcessiveWithdraw { max_amount })
         } else {
             let balance = token_manager.token_balance.checked_sub(withdraw_amount)?;
             token_manager.token_balance = balance;
 
-            bank(deps.storage).save(sender_address_raw, &token_manager)?;
+            // drop the entry of a staker who is fully withdrawn instead of keeping it around empty
+            if balance.is_zero()
+                && token_manager.locked_tokens.is_empty()
+                && token_manager.unbonding.is_empty()
+            {
+                bank(deps.storage).remove(sender_address_raw);
+            } else {
+                bank(deps.storage).save(sender_address_raw, &token_manager)?;
+            }
 
             let mut state = config(deps.storage).load()?;
             let staked_tokens = state.staked_tokens.checked_sub(withdraw_amount)?;
             state.staked_tokens = staked_tokens;
             config(deps.storage).save(&state)?;
 
-            Ok(send_tokens(
+            Ok(release_tokens(
+                &state,
                 &info.sender,
-                vec![coin(withdraw_amount.u128(), &state.denom)],
+                withdraw_amount,
                 "approve",
-            ))
+            )?)
         }
     } else {
         Err(ContractError::PollNoStake {})
     }
 }
 
-/// validate_description returns an error if the description is invalid
-fn validate_description(description: &str) -> Result<(), ContractError> {
-    if (description.len() as u64) < MIN_DESC_LENGTH {
-        Err(ContractError::DescriptionTooShort {
-            min_desc_length: MIN_DESC_LENGTH,
-        })
-    } else if (description.len() as u64) > MAX_DESC_LENGTH {
-        Err(ContractError::DescriptionTooLong {
-            max_desc_length: MAX_DESC_LENGTH,
-        })
-    } else {
-        Ok(())
-    }
-}
-
-/// validate_quorum_percentage returns an error if the quorum_percentage is invalid
-/// (we require 0-100)
-fn validate_quorum_percentage(quorum_percentage: Option<u8>) -> Result<(), ContractError> {
-    match quorum_percentage {
-        Some(qp) => {
-            if qp > 100 {
-                return Err(ContractError::PollQuorumPercentageMismatch {
-                    quorum_percentage: qp,
-                });
-            }
-            Ok(())
-        }
-        None => Ok(()),
-    }
-}
-
-/// validate_end_height returns an error if the poll ends in the past
-fn validate_end_height(end_height: Option<u64>, env: Env) -> Result<(), ContractError> {
-    if end_height.is_some() && env.block.height >= end_height.unwrap() {
-        Err(ContractError::PollCannotEndInPast {})
-    } else {
-        Ok(())
-    }
-}
-
-/// create a new poll
-pub fn create_poll(
+/*
+ * First step of a withdrawal when the contract has an unbonding period. The amount leaves the
+ * stake right away, so it no longer votes or counts towards quorum, but it is only released
+ * to the staker by ClaimUnbonded once the unbonding period is over.
+ */
+pub fn unbond(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    quorum_percentage: Option<u8>,
-    description: String,
-    start_height: Option<u64>,
-    end_height: Option<u64>,
+    amount: Option<Uint128>,
 ) -> Result<Response, ContractError> {
-    validate_quorum_percentage(quorum_percentage)?;
-    validate_end_height(end_height, env.clone())?;
-    validate_description(&description)?;
-    
-    // Poll id is always incrementing by one
+    let sender_address_raw = info.sender.as_str().as_bytes();
+    let mut token_manager = match bank_read(deps.storage).may_load(sender_address_raw)? {
+        Some(token_manager) => token_manager,
+        None => return Err(ContractError::PollNoStake {}),
+    };
 
-    let mut state = config(deps.storage).load()?;
-    let poll_count = state.poll_count;
-    let poll_id = poll_count + 1;
-    state.poll_count = poll_id;
+    let locked = open_locked_sum(deps.storage, &token_manager, env.block.height)?;
+    let unbond_amount = amount.unwrap_or(token_manager.token_balance);
+    if locked + unbond_amount > token_manager.token_balance {
+        let max_amount = token_manager.token_balance.saturating_sub(locked);
+        return Err(ContractError::ExcessiveWithdraw { max_amount });
+    }
 
-    let new_poll = Poll {
-        creator: info.sender,
-        status: PollStatus::InProgress,
-        quorum_percentage,
-        yes_votes: Uint128::zero(),
-        no_votes: Uint128::zero(),
-        voters: vec![],
-        voter_info: vec![],
-        end_height: end_height.unwrap_or(env.block.height + DEFAULT_END_HEIGHT_BLOCKS),
-        start_height,
-        description,
-    };
-    let key = state.poll_count.to_be_bytes();
-    poll(deps.storage).save(&key, &new_poll)?;
+    let mut state = config(deps.storage).load()?;
+    let release_height = env.block.height + state.unbonding_period.unwrap_or(0);
+    token_manager.token_balance = token_manager.token_balance.checked_sub(unbond_amount)?;
+    token_manager
+        .unbonding
+        .push((release_height, unbond_amount));
+    bank(deps.storage).save(sender_address_raw, &token_manager)?;
 
+    state.staked_tokens = state.staked_tokens.checked_sub(unbond_amount)?;
     config(deps.storage).save(&state)?;
 
     let r = Response {
         submessages: vec![],
         messages: vec![],
         attributes: vec![
-            attr("action", "create_poll"),
-            attr("creator", new_poll.creator),
-            attr("poll_id", &poll_id),
-            attr("quorum_percentage", quorum_percentage.unwrap_or(0)),
-            attr("end_height", new_poll.end_height),
-            attr("start_height", start_height.unwrap_or(0)),
+            attr("action", "unbond"),
+            attr("amount", &unbond_amount),
+            attr("release_height", &release_height),
         ],
-        data: Some(to_binary(&CreatePollResponse { poll_id })?),
+        data: None,
     };
     Ok(r)
 }
 
-/*
- * Ends a poll. Only the creator of a given poll can end that poll.
- */
-pub fn end_poll(
+/// sends the sender every unbonded amount whose release height has been reached
+pub fn claim_unbonded(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    poll_id: u64,
 ) -> Result<Response, ContractError> {
-    let key = &poll_id.to_be_bytes();
-    let mut a_poll = poll(deps.storage).load(key)?;
+    let sender_address_raw = info.sender.as_str().as_bytes();
+    let mut token_manager = match bank_read(deps.storage).may_load(sender_address_raw)? {
+        Some(token_manager) => token_manager,
+        None => return Err(ContractError::NoMaturedUnbonding {}),
+    };
 
-    if a_poll.creator != info.sender {
-        return Err(ContractError::PollNotCreator {
-            creator: a_poll.creator.to_string(),
-            sender: info.sender.to_string(),
-        });
+    let (matured, pending): (Vec<_>, Vec<_>) = token_manager
+        .unbonding
+        .iter()
+        .partition(|(release_height, _)| *release_height <= env.block.height);
+    let claim_amount: Uint128 = matured.iter().map(|(_, amount)| *amount).sum();
+    if claim_amount.is_zero() {
+        return Err(ContractError::NoMaturedUnbonding {});
     }
 
-    if a_poll.status != PollStatus::InProgress {
-        return Err(ContractError::PollNotInProgress {});
+    token_manager.unbonding = pending;
+    if token_manager.token_balance.is_zero()
+        && token_manager.locked_tokens.is_empty()
+        && token_manager.unbonding.is_empty()
+    {
+        bank(deps.storage).remove(sender_address_raw);
+    } else {
+        bank(deps.storage).save(sender_address_raw, &token_manager)?;
     }
 
-    if let Some(start_height) = a_poll.start_height {
-        if start_height > env.block.height {
-            return Err(ContractError::PoolVotingPeriodNotStarted { start_height });
-        }
-    }
+    let state = config_read(deps.storage).load()?;
+    Ok(release_tokens(
+        &state,
+        &info.sender,
+        claim_amount,
+        "claim_unbonded",
+    )?)
+}
 
-    if a_poll.end_height > env.block.height {
-        return Err(ContractError::PollVotingPeriodNotExpired {
-            expire_height: a_poll.end_height,
-        });
+// Move free (unlocked) stake to another address. The total staked amount is unchanged.
+pub fn transfer_stake(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    to: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let recipient = deps.api.addr_validate(&to)?;
+    let sender_key = info.sender.as_str().as_bytes();
+
+    let mut sender_manager = match bank_read(deps.storage).may_load(sender_key)? {
+        Some(token_manager) => token_manager,
+        None => return Err(ContractError::PollNoStake {}),
+    };
+
+    let locked = open_locked_sum(deps.storage, &sender_manager, env.block.height)?;
+    if locked + amount > sender_manager.token_balance {
+        let free_balance = sender_manager.token_balance.saturating_sub(locked);
+        return Err(ContractError::InsufficientFreeBalance { free_balance });
     }
+    sender_manager.token_balance = sender_manager.token_balance.checked_sub(amount)?;
+    bank(deps.storage).save(sender_key, &sender_manager)?;
 
-    let mut no = 0u128;
-    let mut yes = 0u128;
+    let recipient_key = recipient.as_str().as_bytes();
+    let mut recipient_manager = bank_read(deps.storage)
+        .may_load(recipient_key)?
+        .unwrap_or_default();
+    recipient_manager.token_balance += amount;
+    bank(deps.storage).save(recipient_key, &recipient_manager)?;
 
-    for voter in &a_poll.voter_info {
-        if voter.vote == "yes" {
-            yes += voter.weight.u128();
-        } else {
-            no += voter.weight.u128();
-        }
+    let r = Response {
+        submessages: vec![],
+        messages: vec![],
+        attributes: vec![
+            attr("action", "transfer_stake"),
+            attr("from", &info.sender),
+            attr("to", &recipient),
+            attr("amount", &amount),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+// Merge the token manager of `from` into the one of `to` after an address migration,
+// then delete the `from` entry. Only the owner can do this.
+pub fn merge_stake(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    from: String,
+    to: String,
+) -> Result<Response, ContractError> {
+    let state = config_read(deps.storage).load()?;
+    if info.sender != state.owner {
+        return Err(ContractError::Unauthorized {});
     }
-    let tallied_weight = yes + no;
 
-    let mut rejected_reason = "";
-    let mut passed = false;
+    let from = deps.api.addr_validate(&from)?;
+    let to = deps.api.addr_validate(&to)?;
+    // merging an address into itself would double its stake and then delete it
+    if from == to {
+        return Err(ContractError::MergeIntoSelf {});
+    }
+    let from_key = from.as_str().as_bytes();
+    let to_key = to.as_str().as_bytes();
 
-    if tallied_weight > 0 {
-        let state = config_read(deps.storage).load()?;
+    let from_manager = match bank_read(deps.storage).may_load(from_key)? {
+        Some(token_manager) => token_manager,
+        None => return Err(ContractError::PollNoStake {}),
+    };
+    let mut to_manager = bank_read(deps.storage)
+        .may_load(to_key)?
+        .unwrap_or_default();
 
-        let staked_weight = deps
-            .querier
-            .query_balance(&env.contract.address, &state.denom)
-            .unwrap()
-            .amount
-            .u128();
+    to_manager.token_balance += from_manager.token_balance;
 
-        if staked_weight == 0 {
-            return Err(ContractError::PollNoStake {});
+    // both addresses may have voted in the same poll, in which case the locks add up
+    for (poll_id, weight) in from_manager.locked_tokens {
+        match to_manager
+            .locked_tokens
+            .iter_mut()
+            .find(|(k, _)| k == &poll_id)
+        {
+            Some((_, locked)) => *locked += weight,
+            None => to_manager.locked_tokens.push((poll_id, weight)),
         }
 
-        let quorum = ((tallied_weight / staked_weight) * 100) as u8;
-        if a_poll.quorum_percentage.is_some() && quorum < a_poll.quorum_percentage.unwrap() {
-            // Quorum: More than quorum_percentage of the total staked tokens at the end of the voting
-            // period need to have participated in the vote.
-            rejected_reason = "Quorum not reached";
-        } else if yes > tallied_weight / 2 {
-            //Threshold: More than 50% of the tokens that participated in the vote
-            // (after excluding “Abstain” votes) need to have voted in favor of the proposal (“Yes”).
-            a_poll.status = PollStatus::Passed;
-            passed = true;
-        } else {
-            rejected_reason = "Threshold not reached";
+        // end_poll unlocks tokens per voter, so the poll must point at the new address. If the
+        // new address voted too, its record already releases the merged lock and the old record
+        // stays under the old address, so each address keeps a single record.
+        let poll_key = &poll_id.to_be_bytes();
+        if let Some(mut a_poll) = poll_read(deps.storage).may_load(poll_key)? {
+            if !has_voted(&to, &a_poll) {
+                for (voter, _) in a_poll.voters.iter_mut() {
+                    if voter == &from {
+                        *voter = to.clone();
+                    }
+                }
+            }
+            // a reservation of the old address moves over, adding up like the locks do
+            if let Some(position) = a_poll.reservations.iter().position(|(r, _)| r == &from) {
+                let (_, weight) = a_poll.reservations.remove(position);
+                match a_poll.reservations.iter_mut().find(|(r, _)| r == &to) {
+                    Some((_, reserved)) => *reserved += weight,
+                    None => a_poll.reservations.push((to.clone(), weight)),
+                }
+            }
+            poll(deps.storage).save(poll_key, &a_poll)?;
         }
-    } else {
-        rejected_reason = "Quorum not reached";
     }
-    if !passed {
-        a_poll.status = PollStatus::Rejected
+
+    // commitments lock nothing, so they are found through the open polls. Two commitments
+    // can't be combined into one, so the merge waits until the poll is over.
+    for poll_id in active_poll_ids(deps.storage)? {
+        let poll_key = &poll_id.to_be_bytes();
+        let mut a_poll = poll_read(deps.storage).load(poll_key)?;
+        if let Some(position) = a_poll.commitments.iter().position(|(c, _)| c == &from) {
+            if a_poll.commitments.iter().any(|(c, _)| c == &to) {
+                return Err(ContractError::CommitmentConflict { poll_id });
+            }
+            a_poll.commitments[position].0 = to.clone();
+            poll(deps.storage).save(poll_key, &a_poll)?;
+        }
     }
-    poll(deps.storage).save(key, &a_poll)?;
 
-    for voter in &a_poll.voters {
-        unlock_tokens(deps.storage, voter, poll_id)?;
+    for poll_id in from_manager.participated_polls {
+        if !to_manager.participated_polls.contains(&poll_id) {
+            to_manager.participated_polls.push(poll_id);
+        }
     }
+    to_manager.unbonding.extend(from_manager.unbonding);
 
-    let attributes = vec![
-        attr("action", "end_poll"),
-        attr("poll_id", &poll_id),
-        attr("rejected_reason", rejected_reason),
-        attr("passed", &passed),
-    ];
+    bank(deps.storage).save(to_key, &to_manager)?;
+    bank(deps.storage).remove(from_key);
 
     let r = Response {
         submessages: vec![],
         messages: vec![],
-        attributes,
+        attributes: vec![
+            attr("action", "merge_stake"),
+            attr("from", &from),
+            attr("to", &to),
+        ],
         data: None,
     };
     Ok(r)
 }
 
-// unlock voter's tokens in a given poll
-fn unlock_tokens(
-    storage: &mut dyn Storage,
-    voter: &Addr,
-    poll_id: u64,
-) -> Result<Response, ContractError> {
-    let voter_key = &voter.as_str().as_bytes();
-    let mut token_manager = bank_read(storage).load(voter_key).unwrap();
-
-    // unlock entails removing the mapped poll_id, retaining the rest
-    token_manager.locked_tokens.retain(|(k, _)| k != &poll_id);
-    bank(storage).save(voter_key, &token_manager)?;
-    Ok(Response::default())
+/// validate_description returns an error if the description is invalid for the length
+/// limits set at instantiation
+fn validate_description(storage: &dyn Storage, description: &str) -> Result<(), ContractError> {
+    let state = config_read(storage).load()?;
+    if (description.len() as u64) < state.min_desc_length {
+        Err(ContractError::DescriptionTooShort {
+            min_desc_length: state.min_desc_length,
+        })
+    } else if (description.len() as u64) > state.max_desc_length {
+        Err(ContractError::DescriptionTooLong {
+            max_desc_length: state.max_desc_length,
+        })
+    } else if description.trim().is_empty() {
+        // long enough, but only whitespace
+        Err(ContractError::DescriptionBlank {})
+    } else {
+        Ok(())
+    }
 }
 
-// finds the largest locked amount in participated polls.
-fn locked_amount(voter: &[u8], storage: &dyn Storage) -> Uint128 {
-    let token_manager = bank_read(storage).load(voter).unwrap();
-    token_manager
-        .locked_tokens
-        .iter()
-        .map(|(_, v)| *v)
-        .max()
-        .unwrap_or_default()
+/// validate_comment returns an error if the optional vote comment is too long
+fn validate_comment(comment: &Option<String>) -> Result<(), ContractError> {
+    match comment {
+        Some(c) if (c.len() as u64) > MAX_COMMENT_LENGTH => Err(ContractError::CommentTooLong {
+            max_comment_length: MAX_COMMENT_LENGTH,
+        }),
+        _ => Ok(()),
+    }
 }
 
-fn has_voted(voter: &Addr, a_poll: &Poll) -> bool {
-    a_poll.voters.iter().any(|i| i == voter)
+// cosmos sdk denom rules: a letter followed by 2 to 127 letters, digits or any of / : . _ -
+fn validate_denom(denom: &str) -> Result<(), ContractError> {
+    let mut chars = denom.chars();
+    let starts_with_letter = matches!(chars.next(), Some(c) if c.is_ascii_alphabetic());
+    let valid_rest = chars.all(|c| c.is_ascii_alphanumeric() || "/:._-".contains(c));
+    if !starts_with_letter || !valid_rest || denom.len() < 3 || denom.len() > 128 {
+        return Err(ContractError::InvalidDenom {
+            denom: denom.to_string(),
+        });
+    }
+    Ok(())
 }
 
-pub fn cast_vote(
-    deps: DepsMut,
-    _env: Env,
-    info: MessageInfo,
-    poll_id: u64,
-    vote: String,
-    weight: Uint128,
-) -> Result<Response, ContractError> {
-    let poll_key = &poll_id.to_be_bytes();
-    let state = config_read(deps.storage).load()?;
-    if poll_id == 0 || state.poll_count > poll_id {
-        return Err(ContractError::PollNotExist {});
+fn validate_max_self_stake_percentage(percentage: Option<u8>) -> Result<(), ContractError> {
+    match percentage {
+        Some(pct) if pct == 0 || pct > 100 => {
+            Err(ContractError::InvalidSelfStakePercentage { percentage: pct })
+        }
+        _ => Ok(()),
     }
+}
 
-    let mut a_poll = poll(deps.storage).load(poll_key)?;
+fn validate_min_vote_percentage(percentage: Option<u8>) -> Result<(), ContractError> {
+    match percentage {
+        Some(pct) if pct > 100 => Err(ContractError::InvalidMinVotePercentage { percentage: pct }),
+        _ => Ok(()),
+    }
+}
 
-    if a_poll.status != PollStatus::InProgress {
-        return Err(ContractError::PollNotInProgress {});
+// an extension has to add blocks, and its threshold is a percentage of the staked weight
+fn validate_auto_extend(auto_extend: &Option<AutoExtend>) -> Result<(), ContractError> {
+    match auto_extend {
+        Some(auto_extend) if auto_extend.blocks == 0 || auto_extend.threshold > 100 => {
+            Err(ContractError::InvalidAutoExtend {})
+        }
+        _ => Ok(()),
     }
+}
 
-    if has_voted(&info.sender, &a_poll) {
-        return Err(ContractError::PollSenderVoted {});
+/// validate_quorum_percentage returns an error if the quorum_percentage is invalid
+/// (we require 0-100)
+fn validate_quorum_percentage(quorum_percentage: Option<u8>) -> Result<(), ContractError> {
+    match quorum_percentage {
+        Some(qp) => {
+            if qp > 100 {
+                return Err(ContractError::PollQuorumPercentageMismatch {
+                    quorum_percentage: qp,
+                });
+            }
+            Ok(())
+        }
+        None => Ok(()),
     }
+}
 
-    let key = info.sender.as_str().as_bytes();
-    let mut token_manager = bank_read(deps.storage).may_load(key)?.unwrap_or_default();
+/// validate_desc_lengths returns an error if no description length could be accepted
+fn validate_desc_lengths(min_desc_length: u64, max_desc_length: u64) -> Result<(), ContractError> {
+    if min_desc_length > max_desc_length {
+        Err(ContractError::InvalidDescLengths {
+            min_desc_length,
+            max_desc_length,
+        })
+    } else {
+        Ok(())
+    }
+}
 
-    if token_manager.token_balance < weight {
-        return Err(ContractError::PollInsufficientStake {});
+/// validate_end_height returns an error if the poll ends in the past
+fn validate_end_height(end_height: Option<u64>, env: Env) -> Result<(), ContractError> {
+    if end_height.is_some() && env.block.height >= end_height.unwrap() {
+        Err(ContractError::PollCannotEndInPast {})
+    } else {
+        Ok(())
     }
-    token_manager.participated_polls.push(poll_id);
-    token_manager.locked_tokens.push((poll_id, weight));
-    bank(deps.storage).save(key, &token_manager)?;
+}
 
-    a_poll.voters.push(info.sender.clone());
+/// validate_max_end_height returns an error if the poll would end after the configured cap
+fn validate_max_end_height(
+    end_height: u64,
+    max_absolute_end_height: Option<u64>,
+) -> Result<(), ContractError> {
+    match max_absolute_end_height {
+        Some(max_end_height) if end_height > max_end_height => {
+            Err(ContractError::PollEndsTooLate { max_end_height })
+        }
+        _ => Ok(()),
+    }
+}
 
-    let voter_info = Voter { vote, weight };
+/// validate_parent_poll returns an error unless the parent poll exists and is still in progress,
+/// amendments can only be attached while the parent is open
+fn validate_parent_poll(
+    storage: &dyn Storage,
+    parent_poll_id: Option<u64>,
+) -> Result<(), ContractError> {
+    if let Some(parent_poll_id) = parent_poll_id {
+        match poll_read(storage).may_load(&parent_poll_id.to_be_bytes())? {
+            Some(parent) if parent.status == PollStatus::InProgress => {}
+            Some(_) => return Err(ContractError::PollNotInProgress {}),
+            None => return Err(ContractError::PollNotExist {}),
+        }
+    }
+    Ok(())
+}
 
-    a_poll.voter_info.push(voter_info);
-    poll(deps.storage).save(poll_key, &a_poll)?;
+/// pause or resume poll creation; voting, staking and withdrawing are unaffected
+pub fn set_creation_paused(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    paused: bool,
+    reason: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut state = config(deps.storage).load()?;
+    if info.sender != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
 
-    let attributes = vec![
-        attr("action", "vote_casted"),
-        attr("poll_id", &poll_id),
-        attr("weight", &weight),
-        attr("voter", &info.sender),
-    ];
+    state.creation_paused = paused;
+    // a reason only describes the current pause
+    state.pause_reason = if paused { reason } else { None };
+    config(deps.storage).save(&state)?;
 
     let r = Response {
         submessages: vec![],
         messages: vec![],
-        attributes,
+        attributes: vec![
+            attr("action", "set_creation_paused"),
+            attr("paused", &paused),
+        ],
         data: None,
     };
     Ok(r)
 }
 
-fn send_tokens(to_address: &Addr, amount: Vec<Coin>, action: &str) -> Response {
-    let attributes = vec![attr("action", action), attr("to", to_address.clone())];
+/// first step of an ownership transfer: the new owner only takes over once it accepts, so a
+/// mistyped address can't lock the owner out
+pub fn transfer_ownership(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    new_owner: String,
+) -> Result<Response, ContractError> {
+    let mut state = config(deps.storage).load()?;
+    if info.sender != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
 
-    Response {
+    let new_owner = deps.api.addr_validate(new_owner.as_str())?;
+    state.pending_owner = Some(new_owner.clone());
+    config(deps.storage).save(&state)?;
+
+    let r = Response {
         submessages: vec![],
-        messages: vec![CosmosMsg::Bank(BankMsg::Send {
-            to_address: to_address.to_string(),
-            amount,
-        })],
-        attributes,
+        messages: vec![],
+        attributes: vec![
+            attr("action", "transfer_ownership"),
+            attr("pending_owner", &new_owner),
+        ],
         data: None,
+    };
+    Ok(r)
+}
+
+/// second step of an ownership transfer, called by the pending owner
+pub fn accept_ownership(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let mut state = config(deps.storage).load()?;
+    if state.pending_owner.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
     }
+
+    state.owner = info.sender.clone();
+    state.pending_owner = None;
+    config(deps.storage).save(&state)?;
+
+    let r = Response {
+        submessages: vec![],
+        messages: vec![],
+        attributes: vec![
+            attr("action", "accept_ownership"),
+            attr("owner", &info.sender),
+        ],
+        data: None,
+    };
+    Ok(r)
 }
 
-#[entry_point]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::Config {} => to_binary(&config_read(deps.storage).load()?),
-        QueryMsg::TokenStake { address } => {
-            token_balance(deps, deps.api.addr_validate(address.as_str())?)
+// whether a poll which is still in progress already uses this exact description
+fn has_open_poll_with(storage: &dyn Storage, description: &str) -> StdResult<bool> {
+    for poll_id in active_poll_ids(storage)? {
+        let a_poll = poll_read(storage).load(&poll_id.to_be_bytes())?;
+        if a_poll.description == description {
+            return Ok(true);
         }
-        QueryMsg::Poll { poll_id } => query_poll(deps, poll_id),
     }
+    Ok(false)
 }
 
-fn query_poll(deps: Deps, poll_id: u64) -> StdResult<Binary> {
-    let key = &poll_id.to_be_bytes();
+/// update any of the owner settable parameters at once, leaving the ones which are None unchanged.
+/// clear_max_absolute_end_height removes the cap and takes precedence over a new one
+#[allow(clippy::too_many_arguments)]
+pub fn update_config(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    default_poll_duration: Option<u64>,
+    reject_duplicate_descriptions: Option<bool>,
+    max_absolute_end_height: Option<u64>,
+    clear_max_absolute_end_height: Option<bool>,
+    denom: Option<String>,
+    min_stake_amount: Option<Uint128>,
+    min_desc_length: Option<u64>,
+    max_desc_length: Option<u64>,
+) -> Result<Response, ContractError> {
+    let mut state = config(deps.storage).load()?;
+    if info.sender != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
 
-    let poll = match poll_read(deps.storage).may_load(key)? {
-        Some(poll) => Some(poll),
-        None => return Err(StdError::generic_err("Poll does not exist")),
+    if let Some(default_poll_duration) = default_poll_duration {
+        // a poll created without an end height would close in the block it was created in
+        if default_poll_duration == 0 {
+            return Err(ContractError::InvalidPollDuration {});
+        }
+        state.default_poll_duration = default_poll_duration;
     }
-    .unwrap();
+    if let Some(reject_duplicate_descriptions) = reject_duplicate_descriptions {
+        state.reject_duplicate_descriptions = reject_duplicate_descriptions;
+    }
+    if let Some(max_absolute_end_height) = max_absolute_end_height {
+        state.max_absolute_end_height = Some(max_absolute_end_height);
+    }
+    if clear_max_absolute_end_height == Some(true) {
+        state.max_absolute_end_height = None;
+    }
+    if let Some(denom) = denom {
+        validate_denom(&denom)?;
+        // the staked balances could no longer be withdrawn in the denom they were staked in,
+        // and unbonding amounts no longer count as staked but are still paid out in it
+        if denom != state.denom
+            && (!state.staked_tokens.is_zero() || has_pending_unbonding(deps.storage)?)
+        {
+            return Err(ContractError::DenomChangeWithStake {});
+        }
+        state.denom = denom;
+    }
+    if let Some(min_stake_amount) = min_stake_amount {
+        state.min_stake_amount = min_stake_amount;
+    }
+    if let Some(min_desc_length) = min_desc_length {
+        state.min_desc_length = min_desc_length;
+    }
+    if let Some(max_desc_length) = max_desc_length {
+        state.max_desc_length = max_desc_length;
+    }
+    validate_desc_lengths(state.min_desc_length, state.max_desc_length)?;
+    config(deps.storage).save(&state)?;
 
-    let resp = PollResponse {
-        creator: poll.creator.to_string(),
-        status: poll.status,
-        quorum_percentage: poll.quorum_percentage,
-        end_height: Some(poll.end_height),
-        start_height: poll.start_height,
-        description: poll.description,
+    let r = Response {
+        submessages: vec![],
+        messages: vec![],
+        attributes: vec![attr("action", "update_config")],
+        data: None,
     };
-    to_binary(&resp)
+    Ok(r)
 }
 
-fn token_balance(deps: Deps, address: Addr) -> StdResult<Binary> {
-    let token_manager = bank_read(deps.storage)
-        .may_load(address.as_str().as_bytes())?
-        .unwrap_or_default();
+/// create a new poll
+#[allow(clippy::too_many_arguments)]
+pub fn create_poll(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    quorum_percentage: Option<u8>,
+    description: String,
+    start_height: Option<u64>,
+    end_height: Option<u64>,
+    parent_poll_id: Option<u64>,
+    requires_poll: Option<u64>,
+    max_self_stake_percentage: Option<u8>,
+    commit_reveal: bool,
+    max_total_weight: Option<Uint128>,
+    auto_extend: Option<AutoExtend>,
+    min_vote_percentage: Option<u8>,
+) -> Result<Response, ContractError> {
+    validate_quorum_percentage(quorum_percentage)?;
+    validate_max_self_stake_percentage(max_self_stake_percentage)?;
+    validate_min_vote_percentage(min_vote_percentage)?;
+    validate_auto_extend(&auto_extend)?;
+    validate_end_height(end_height, env.clone())?;
+    validate_description(deps.storage, &description)?;
+    validate_parent_poll(deps.storage, parent_poll_id)?;
+    if let Some(required_id) = requires_poll {
+        if poll_read(deps.storage)
+            .may_load(&required_id.to_be_bytes())?
+            .is_none()
+        {
+            return Err(ContractError::PollNotExist {});
+        }
+    }
 
-    let resp = TokenStakeResponse {
-        token_balance: token_manager.token_balance,
+    let mut state = config(deps.storage).load()?;
+    if state.creation_paused {
+        return Err(ContractError::CreationPaused {});
+    }
+    if state.reject_duplicate_descriptions && has_open_poll_with(deps.storage, &description)? {
+        return Err(ContractError::DuplicateDescription {});
+    }
+
+    // by default the poll stays open for the full duration after it opens
+    let opens_at = start_height
+        .unwrap_or(env.block.height)
+        .max(env.block.height);
+    let end_height = end_height.unwrap_or(opens_at + state.default_poll_duration);
+    validate_max_end_height(end_height, state.max_absolute_end_height)?;
+
+    // Poll id is always incrementing by one
+    let poll_count = state.poll_count;
+    let poll_id = poll_count + 1;
+    state.poll_count = poll_id;
+
+    let new_poll = Poll {
+        creator: info.sender,
+        status: PollStatus::InProgress,
+        quorum_percentage,
+        yes_votes: Uint128::zero(),
+        no_votes: Uint128::zero(),
+        abstain_votes: Uint128::zero(),
+        voters: vec![],
+        end_height,
+        start_height,
+        description,
+        parent_poll_id,
+        requires_poll,
+        max_self_stake_percentage,
+        commit_reveal,
+        commitments: vec![],
+        // votes of a commit-reveal poll are only revealed after the voting window
+        reveal_end_height: if commit_reveal {
+            Some(end_height + REVEAL_PERIOD_BLOCKS)
+        } else {
+            None
+        },
+        max_total_weight,
+        rejected_reason: None,
+        reservations: vec![],
+        auto_extend,
+        extensions: 0,
+        min_vote_percentage,
     };
+    let key = state.poll_count.to_be_bytes();
+    poll(deps.storage).save(&key, &new_poll)?;
+    active_poll(deps.storage).save(&key, &true)?;
+    config(deps.storage).save(&state)?;
 
-    to_binary(&resp)
+    let r = Response {
+        submessages: vec![],
+        messages: vec![],
+        attributes: vec![
+            attr("action", "create_poll"),
+            attr("creator", new_poll.creator),
+            attr("poll_id", &poll_id),
+            attr("quorum_percentage", quorum_percentage.unwrap_or(0)),
+            attr("end_height", new_poll.end_height),
+            attr("start_height", start_height.unwrap_or(0)),
+        ],
+        data: Some(to_binary(&CreatePollResponse { poll_id })?),
+    };
+    Ok(r)
+}
+
+/// store the parameters of a recurring poll under a name, replacing any template of that name
+pub fn save_template(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    name: String,
+    params: CreatePollParams,
+) -> Result<Response, ContractError> {
+    let state = config_read(deps.storage).load()?;
+    if info.sender != state.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+    validate_quorum_percentage(params.quorum_percentage)?;
+    validate_max_self_stake_percentage(params.max_self_stake_percentage)?;
+
+    template(deps.storage).save(name.as_bytes(), &params)?;
+
+    let r = Response {
+        submessages: vec![],
+        messages: vec![],
+        attributes: vec![attr("action", "save_template"), attr("name", &name)],
+        data: None,
+    };
+    Ok(r)
+}
+
+/// create a poll with the parameters of a saved template, the voting period starts now
+pub fn create_from_template(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    name: String,
+    description: String,
+) -> Result<Response, ContractError> {
+    let params = match template_read(deps.storage).may_load(name.as_bytes())? {
+        Some(params) => params,
+        None => return Err(ContractError::TemplateNotFound { name }),
+    };
+    let end_height = params.duration.map(|duration| env.block.height + duration);
+
+    create_poll(
+        deps,
+        env,
+        info,
+        params.quorum_percentage,
+        description,
+        None,
+        end_height,
+        None,
+        None,
+        params.max_self_stake_percentage,
+        params.commit_reveal,
+        params.max_total_weight,
+        None,
+        None,
+    )
+}
+
+/*
+ * Ends a poll. Only the creator of a given poll can end that poll.
+ */
+pub fn end_poll(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: u64,
+) -> Result<Response, ContractError> {
+    let key = &poll_id.to_be_bytes();
+    let mut a_poll = poll(deps.storage).load(key)?;
+
+    if a_poll.creator != info.sender {
+        return Err(ContractError::PollNotCreator {
+            creator: a_poll.creator.to_string(),
+            sender: info.sender.to_string(),
+        });
+    }
+
+    if a_poll.status == PollStatus::Cancelled {
+        return Err(ContractError::PollCancelled {});
+    }
+    if a_poll.status != PollStatus::InProgress {
+        return Err(ContractError::PollNotInProgress {});
+    }
+
+    if let Some(start_height) = a_poll.start_height {
+        if start_height > env.block.height {
+            return Err(ContractError::PoolVotingPeriodNotStarted { start_height });
+        }
+    }
+
+    // a commit-reveal poll can't be ended while its votes can still be revealed
+    let closes_at = poll_closes_at(&a_poll);
+    if closes_at > env.block.height {
+        return Err(ContractError::PollVotingPeriodNotExpired {
+            expire_height: closes_at,
+        });
+    }
+
+    let result = tally_poll(deps.as_ref(), &a_poll)?;
+
+    // a poll which is only just short of its quorum gets more blocks instead of being rejected
+    let max_absolute_end_height = config_read(deps.storage).load()?.max_absolute_end_height;
+    if let Some(end_height) = auto_extended_end_height(
+        deps.storage,
+        poll_id,
+        &a_poll,
+        &result,
+        max_absolute_end_height,
+        env.block.height,
+    )? {
+        a_poll.extensions += 1;
+        a_poll.end_height = end_height;
+        if a_poll.reveal_end_height.is_some() {
+            a_poll.reveal_end_height = Some(end_height + REVEAL_PERIOD_BLOCKS);
+        }
+        poll(deps.storage).save(key, &a_poll)?;
+
+        let r = Response {
+            submessages: vec![],
+            messages: vec![],
+            attributes: vec![
+                attr("action", "auto_extend"),
+                attr("poll_id", &poll_id),
+                attr("end_height", &a_poll.end_height),
+                attr("extensions", &a_poll.extensions),
+            ],
+            data: None,
+        };
+        return Ok(r);
+    }
+
+    a_poll.status = result.status.clone();
+    a_poll.rejected_reason = result.rejected_reason;
+    poll(deps.storage).save(key, &a_poll)?;
+
+    active_poll(deps.storage).remove(key);
+    let mut state = config(deps.storage).load()?;
+    state.active_voting_weight = state
+        .active_voting_weight
+        .checked_sub(result.tallied_weight)?;
+    config(deps.storage).save(&state)?;
+
+    for (voter, _) in &a_poll.voters {
+        unlock_tokens(deps.storage, voter, poll_id)?;
+    }
+    // reservations which were never activated are released as well
+    for (voter, _) in &a_poll.reservations {
+        unlock_tokens(deps.storage, voter, poll_id)?;
+    }
+
+    let attributes = vec![
+        attr("action", "end_poll"),
+        attr("poll_id", &poll_id),
+        attr(
+            "rejected_reason",
+            result
+                .rejected_reason
+                .map(|reason| reason.to_string())
+                .unwrap_or_default(),
+        ),
+        attr("passed", &result.passed),
+        attr("yes_votes", &result.yes_votes),
+        attr("no_votes", &result.no_votes),
+        attr("tallied_weight", &result.tallied_weight),
+        attr("quorum_reached", &result.quorum_reached),
+        attr("staked_weight", &result.staked_weight),
+    ];
+
+    let r = Response {
+        submessages: vec![],
+        messages: vec![],
+        attributes,
+        data: Some(to_binary(&result)?),
+    };
+    Ok(r)
+}
+
+/*
+ * Lets the creator abort a poll which is still in progress, at any height. Nothing is
+ * tallied, the poll is taken out of the open polls and every voter's lock in it is released.
+ */
+pub fn cancel_poll(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: u64,
+) -> Result<Response, ContractError> {
+    let key = &poll_id.to_be_bytes();
+    let mut a_poll = match poll_read(deps.storage).may_load(key)? {
+        Some(a_poll) => a_poll,
+        None => return Err(ContractError::PollNotExist {}),
+    };
+
+    if a_poll.creator != info.sender {
+        return Err(ContractError::PollNotCreator {
+            creator: a_poll.creator.to_string(),
+            sender: info.sender.to_string(),
+        });
+    }
+
+    if a_poll.status != PollStatus::InProgress {
+        return Err(ContractError::PollNotInProgress {});
+    }
+
+    // once voting is over the outcome is settled and the poll can only be ended
+    if a_poll.end_height <= env.block.height {
+        return Err(ContractError::PollVotingPeriodExpired {
+            expire_height: a_poll.end_height,
+        });
+    }
+
+    a_poll.status = PollStatus::Cancelled;
+    poll(deps.storage).save(key, &a_poll)?;
+
+    active_poll(deps.storage).remove(key);
+    let mut state = config(deps.storage).load()?;
+    state.active_voting_weight = state
+        .active_voting_weight
+        .checked_sub(a_poll.yes_votes + a_poll.no_votes + a_poll.abstain_votes)?;
+    config(deps.storage).save(&state)?;
+
+    for (voter, _) in &a_poll.voters {
+        unlock_tokens(deps.storage, voter, poll_id)?;
+    }
+    for (voter, _) in &a_poll.reservations {
+        unlock_tokens(deps.storage, voter, poll_id)?;
+    }
+
+    let r = Response {
+        submessages: vec![],
+        messages: vec![],
+        attributes: vec![attr("action", "cancel_poll"), attr("poll_id", &poll_id)],
+        data: None,
+    };
+    Ok(r)
+}
+
+// converts a raw token weight to the configured weight unit, dropping the remainder.
+// Without a weight unit the raw weight is used as is.
+fn to_weight_units(weight: Uint128, weight_unit: Option<Uint128>) -> Uint128 {
+    match weight_unit {
+        Some(unit) => weight.multiply_ratio(1u128, unit),
+        None => weight,
+    }
+}
+
+/*
+ * The end height end_poll moves the poll to instead of finalizing it, when the poll is only
+ * just short of its quorum, has extensions left and the extension stays within the cap.
+ * The locks lapse at the old end height, so the poll is only extended while every vote is
+ * still backed by stake which no other open poll locks.
+ */
+fn auto_extended_end_height(
+    storage: &dyn Storage,
+    poll_id: u64,
+    a_poll: &Poll,
+    result: &PollResult,
+    max_absolute_end_height: Option<u64>,
+    height: u64,
+) -> StdResult<Option<u64>> {
+    let auto_extend = match &a_poll.auto_extend {
+        Some(auto_extend) => auto_extend,
+        None => return Ok(None),
+    };
+    let quorum_gap = a_poll
+        .quorum_percentage
+        .unwrap_or(0)
+        .saturating_sub(result.quorum);
+    if result.quorum_reached
+        || a_poll.extensions >= auto_extend.max_extensions
+        || quorum_gap > auto_extend.threshold
+    {
+        return Ok(None);
+    }
+
+    let end_height = a_poll.end_height + auto_extend.blocks;
+    if validate_max_end_height(end_height, max_absolute_end_height).is_err()
+        || !votes_still_backed(storage, poll_id, a_poll, height)?
+    {
+        return Ok(None);
+    }
+    Ok(Some(end_height))
+}
+
+// whether each voter's balance still covers their vote on top of their locks in the other
+// polls which lock tokens at this height
+fn votes_still_backed(
+    storage: &dyn Storage,
+    poll_id: u64,
+    a_poll: &Poll,
+    height: u64,
+) -> StdResult<bool> {
+    for (voter, vote) in &a_poll.voters {
+        let token_manager = bank_read(storage)
+            .may_load(voter.as_str().as_bytes())?
+            .unwrap_or_default();
+        let mut locked_elsewhere = Uint128::zero();
+        for (id, weight) in &token_manager.locked_tokens {
+            if *id == poll_id {
+                continue;
+            }
+            if let Some(other) = poll_read(storage).may_load(&id.to_be_bytes())? {
+                if poll_locks_tokens(&other, height) {
+                    locked_elsewhere += *weight;
+                }
+            }
+        }
+        if token_manager.token_balance < locked_elsewhere + vote.weight {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/*
+ * Computes the result of a poll the way end_poll does, without saving anything.
+ * Used by end_poll itself and by the DryRunEndPoll query.
+ */
+fn tally_poll(deps: Deps, a_poll: &Poll) -> Result<PollResult, ContractError> {
+    let weight_unit = config_read(deps.storage).load()?.weight_unit;
+
+    // cast_vote keeps the running tallies up to date, so there is no need to recount here
+    let yes = a_poll.yes_votes.u128();
+    let no = a_poll.no_votes.u128();
+    let abstain = a_poll.abstain_votes.u128();
+
+    #[cfg(debug_assertions)]
+    {
+        let mut recount_yes = 0u128;
+        let mut recount_no = 0u128;
+        let mut recount_abstain = 0u128;
+        for (_, voter) in &a_poll.voters {
+            let tally = to_weight_units(voter.weight, weight_unit).u128();
+            match voter.vote {
+                VoteOption::Yes => recount_yes += tally,
+                VoteOption::No => recount_no += tally,
+                VoteOption::Abstain => recount_abstain += tally,
+            }
+        }
+        assert_eq!(
+            (recount_yes, recount_no, recount_abstain),
+            (yes, no, abstain)
+        );
+    }
+    // abstains count towards the quorum, but not towards the threshold
+    let tallied_weight = yes + no + abstain;
+
+    let mut rejected_reason = None;
+    let mut passed = false;
+    let mut quorum = 0u8;
+    let mut quorum_reached = false;
+    let mut staked_weight = 0u128;
+
+    if tallied_weight > 0 {
+        let (_, denominator) = quorum_denominator(deps)?;
+        staked_weight = to_weight_units(denominator, weight_unit).u128();
+
+        // multiply before dividing, tallied_weight / staked_weight is almost always 0 in
+        // integer math. Flooring the percentage keeps the boundary inclusive:
+        // quorum >= required  <=>  tallied_weight * 100 >= required * staked_weight
+        if staked_weight > 0 {
+            quorum = Uint128::from(tallied_weight)
+                .multiply_ratio(100u128, staked_weight)
+                .u128()
+                .min(100) as u8;
+        }
+        if staked_weight == 0 {
+            // every voter withdrew once the locks lapsed, the quorum can no longer be measured
+            rejected_reason = Some(RejectReason::QuorumNotReached);
+        } else if a_poll.quorum_percentage.is_some() && quorum < a_poll.quorum_percentage.unwrap() {
+            // Quorum: At least quorum_percentage of the total staked tokens at the end of the voting
+            // period need to have participated in the vote.
+            rejected_reason = Some(RejectReason::QuorumNotReached);
+        } else if yes > (yes + no) / 2 {
+            quorum_reached = true;
+            //Threshold: More than 50% of the tokens that participated in the vote
+            // (after excluding “Abstain” votes) need to have voted in favor of the proposal (“Yes”).
+            passed = true;
+        } else if yes == no && yes > 0 {
+            quorum_reached = true;
+            // an even split is reported on its own rather than as a missed threshold
+            rejected_reason = Some(RejectReason::Tied);
+        } else {
+            quorum_reached = true;
+            rejected_reason = Some(RejectReason::ThresholdNotReached);
+        }
+    } else {
+        rejected_reason = Some(RejectReason::QuorumNotReached);
+    }
+
+    // a conditional poll can only pass once the poll it depends on has passed
+    if let Some(required_id) = a_poll.requires_poll {
+        let required = poll_read(deps.storage).may_load(&required_id.to_be_bytes())?;
+        if !matches!(required, Some(required) if required.status == PollStatus::Passed) {
+            passed = false;
+            rejected_reason = Some(RejectReason::DependencyNotMet);
+        }
+    }
+
+    Ok(PollResult {
+        status: if passed {
+            PollStatus::Passed
+        } else if rejected_reason == Some(RejectReason::Tied) {
+            PollStatus::Tied
+        } else {
+            PollStatus::Rejected
+        },
+        passed,
+        rejected_reason,
+        yes_votes: a_poll.yes_votes,
+        no_votes: a_poll.no_votes,
+        abstain_votes: a_poll.abstain_votes,
+        tallied_weight: Uint128::from(tallied_weight),
+        quorum,
+        quorum_reached,
+        staked_weight: Uint128::from(staked_weight),
+    })
+}
+
+// the basis and total staked weight that a poll's quorum is measured against. The contract
+// balance is not used, anyone can send tokens to the contract without staking them
+fn quorum_denominator(deps: Deps) -> StdResult<(&'static str, Uint128)> {
+    let state = config_read(deps.storage).load()?;
+    Ok(("staked_tokens", state.staked_tokens))
+}
+
+// unlock voter's tokens in a given poll
+fn unlock_tokens(
+    storage: &mut dyn Storage,
+    voter: &Addr,
+    poll_id: u64,
+) -> Result<Response, ContractError> {
+    let voter_key = &voter.as_str().as_bytes();
+    // an address merged into another one no longer has a bank entry
+    let mut token_manager = match bank_read(storage).may_load(voter_key)? {
+        Some(token_manager) => token_manager,
+        None => return Ok(Response::default()),
+    };
+
+    // unlock entails removing the mapped poll_id, retaining the rest
+    token_manager.locked_tokens.retain(|(k, _)| k != &poll_id);
+    bank(storage).save(voter_key, &token_manager)?;
+    Ok(Response::default())
+}
+
+// the height from which a poll can be ended: its end height, or for a commit-reveal poll
+// the end of the reveal period
+fn poll_closes_at(a_poll: &Poll) -> u64 {
+    a_poll.reveal_end_height.unwrap_or(a_poll.end_height)
+}
+
+// Whether a poll still locks its voters' tokens at this height. A poll which can be ended
+// no longer does, even if nobody has called end_poll on it yet.
+fn poll_locks_tokens(a_poll: &Poll, height: u64) -> bool {
+    a_poll.status == PollStatus::InProgress && height < poll_closes_at(a_poll)
+}
+
+// sums the voter's locks across all polls which still lock tokens. Only the balance above
+// this sum is free to vote, withdraw, unbond or transfer
+fn open_locked_sum(
+    storage: &dyn Storage,
+    token_manager: &TokenManager,
+    height: u64,
+) -> StdResult<Uint128> {
+    let mut total = Uint128::zero();
+    for (poll_id, weight) in &token_manager.locked_tokens {
+        if let Some(a_poll) = poll_read(storage).may_load(&poll_id.to_be_bytes())? {
+            if poll_locks_tokens(&a_poll, height) {
+                total += *weight;
+            }
+        }
+    }
+    Ok(total)
+}
+
+// ids of the polls in progress in ascending order
+fn active_poll_ids(storage: &dyn Storage) -> StdResult<Vec<u64>> {
+    active_poll_read(storage)
+        .range(None, None, Order::Ascending)
+        .map(|item| item.map(|(key, _)| poll_id_from_key(&key)))
+        .collect()
+}
+
+// whether any staker still has tokens unbonding, which are paid out in the current denom
+fn has_pending_unbonding(storage: &dyn Storage) -> StdResult<bool> {
+    for item in bank_read(storage).range(None, None, Order::Ascending) {
+        let (_, token_manager) = item?;
+        if !token_manager.unbonding.is_empty() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn has_voted(voter: &Addr, a_poll: &Poll) -> bool {
+    a_poll.voters.iter().any(|(i, _)| i == voter)
+}
+
+// loads a poll which takes plain votes right now, the checks cast_vote runs on the poll itself
+fn load_votable_poll(
+    storage: &dyn Storage,
+    env: &Env,
+    state: &State,
+    poll_id: u64,
+) -> Result<Poll, ContractError> {
+    if poll_id == 0 || poll_id > state.poll_count {
+        return Err(ContractError::PollNotExist {});
+    }
+
+    let a_poll = match poll_read(storage).may_load(&poll_id.to_be_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(ContractError::PollNotExist {}),
+    };
+
+    if a_poll.status != PollStatus::InProgress {
+        return Err(ContractError::PollNotInProgress {});
+    }
+
+    if let Some(start_height) = a_poll.start_height {
+        if start_height > env.block.height {
+            return Err(ContractError::PoolVotingPeriodNotStarted { start_height });
+        }
+    }
+
+    // the poll stops locking tokens at its end height, so it must stop taking votes there too
+    if a_poll.end_height <= env.block.height {
+        return Err(ContractError::PollVotingPeriodExpired {
+            expire_height: a_poll.end_height,
+        });
+    }
+
+    if a_poll.commit_reveal {
+        return Err(ContractError::PollRequiresCommitReveal {});
+    }
+
+    Ok(a_poll)
+}
+
+/*
+ * Checks that the voter can commit the weight to this poll. For a new vote the voter must not
+ * have voted yet; when changing a vote, `replacing` is the weight of the current vote, whose
+ * lock is released by the change. Returns the voter's token manager and the weight as counted
+ * in the tally.
+ */
+fn check_vote_weight(
+    storage: &dyn Storage,
+    env: &Env,
+    voter: &Addr,
+    a_poll: &Poll,
+    state: &State,
+    weight: Uint128,
+    replacing: Option<Uint128>,
+) -> Result<(TokenManager, Uint128), ContractError> {
+    if replacing.is_none() && has_voted(voter, a_poll) {
+        return Err(ContractError::PollSenderVoted {});
+    }
+
+    let token_manager = bank_read(storage)
+        .may_load(voter.as_str().as_bytes())?
+        .unwrap_or_default();
+
+    if token_manager.token_balance < weight {
+        return Err(ContractError::PollInsufficientStake {});
+    }
+
+    // tokens already locked in other open polls can't be voted again
+    let locked = open_locked_sum(storage, &token_manager, env.block.height)?
+        .saturating_sub(replacing.unwrap_or_default());
+    let free_stake = token_manager.token_balance.saturating_sub(locked);
+    if weight > free_stake {
+        return Err(ContractError::InsufficientFreeStake { free_stake });
+    }
+
+    // the poll may only take part of each voter's own stake
+    if let Some(pct) = a_poll.max_self_stake_percentage {
+        let max_weight = token_manager
+            .token_balance
+            .multiply_ratio(pct as u128, 100u128);
+        if weight > max_weight {
+            return Err(ContractError::VoteWeightTooHigh { max_weight });
+        }
+    }
+
+    // and each vote has to carry a share of the total stake
+    if let Some(pct) = a_poll.min_vote_percentage {
+        let min_weight = state.staked_tokens.multiply_ratio(pct as u128, 100u128);
+        if weight < min_weight {
+            return Err(ContractError::VoteWeightTooLow { min_weight });
+        }
+    }
+
+    // the full weight is locked, but the tally counts it in whole weight units
+    let tally = to_weight_units(weight, state.weight_unit);
+    if let Some(weight_unit) = state.weight_unit {
+        if tally.is_zero() {
+            return Err(ContractError::WeightBelowUnit { weight_unit });
+        }
+    }
+
+    // the cap is on the tallied weight, so it counts in weight units too
+    if let Some(max_total_weight) = a_poll.max_total_weight {
+        let tallied_weight =
+            (a_poll.yes_votes + a_poll.no_votes + a_poll.abstain_votes).saturating_sub(
+                to_weight_units(replacing.unwrap_or_default(), state.weight_unit),
+            );
+        if tallied_weight + tally > max_total_weight {
+            return Err(ContractError::PollWeightCapReached { max_total_weight });
+        }
+    }
+    Ok((token_manager, tally))
+}
+
+/*
+ * Checks the voter's stake and applies a vote to the poll and state: locks the weight,
+ * updates the running tally and records the voter. The caller saves the poll and state.
+ * Shared by cast_vote and reveal_vote.
+ */
+#[allow(clippy::too_many_arguments)]
+fn record_vote(
+    storage: &mut dyn Storage,
+    env: &Env,
+    voter: &Addr,
+    poll_id: u64,
+    a_poll: &mut Poll,
+    state: &mut State,
+    vote: VoteOption,
+    weight: Uint128,
+    comment: Option<String>,
+) -> Result<Uint128, ContractError> {
+    let (mut token_manager, tally) =
+        check_vote_weight(storage, env, voter, a_poll, state, weight, None)?;
+    let key = voter.as_str().as_bytes();
+    token_manager.participated_polls.push(poll_id);
+    token_manager.locked_tokens.push((poll_id, weight));
+    bank(storage).save(key, &token_manager)?;
+
+    // update the running tally, end_poll reads it directly
+    match vote {
+        VoteOption::Yes => a_poll.yes_votes += tally,
+        VoteOption::No => a_poll.no_votes += tally,
+        VoteOption::Abstain => a_poll.abstain_votes += tally,
+    }
+
+    let voter_info = Voter {
+        vote,
+        weight,
+        comment,
+        voted_at_height: env.block.height,
+    };
+
+    a_poll.voters.push((voter.clone(), voter_info));
+    state.active_voting_weight += tally;
+    Ok(tally)
+}
+
+pub fn cast_vote(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: u64,
+    vote: VoteOption,
+    weight: Uint128,
+    comment: Option<String>,
+) -> Result<Response, ContractError> {
+    validate_comment(&comment)?;
+
+    let poll_key = &poll_id.to_be_bytes();
+    let mut state = config(deps.storage).load()?;
+    let mut a_poll = load_votable_poll(deps.storage, &env, &state, poll_id)?;
+
+    let tally = record_vote(
+        deps.storage,
+        &env,
+        &info.sender,
+        poll_id,
+        &mut a_poll,
+        &mut state,
+        vote,
+        weight,
+        comment,
+    )?;
+    poll(deps.storage).save(poll_key, &a_poll)?;
+    config(deps.storage).save(&state)?;
+
+    let attributes = vec![
+        attr("action", "vote_casted"),
+        attr("poll_id", &poll_id),
+        attr("weight", &tally),
+        attr("voter", &info.sender),
+    ];
+
+    let r = Response {
+        submessages: vec![],
+        messages: vec![],
+        attributes,
+        data: None,
+    };
+    Ok(r)
+}
+
+/*
+ * Replaces the sender's vote in a poll which is still open with a new option and weight.
+ * The old weight is taken out of the tally and its lock is replaced by the new weight.
+ */
+pub fn change_vote(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: u64,
+    vote: VoteOption,
+    weight: Uint128,
+) -> Result<Response, ContractError> {
+    let poll_key = &poll_id.to_be_bytes();
+    let mut state = config(deps.storage).load()?;
+    let mut a_poll = load_votable_poll(deps.storage, &env, &state, poll_id)?;
+
+    let position = match a_poll.voters.iter().position(|(v, _)| v == &info.sender) {
+        Some(position) => position,
+        None => return Err(ContractError::PollSenderNotVoted {}),
+    };
+    let old_weight = a_poll.voters[position].1.weight;
+    let (mut token_manager, tally) = check_vote_weight(
+        deps.storage,
+        &env,
+        &info.sender,
+        &a_poll,
+        &state,
+        weight,
+        Some(old_weight),
+    )?;
+
+    let old_tally = to_weight_units(old_weight, state.weight_unit);
+    match a_poll.voters[position].1.vote {
+        VoteOption::Yes => a_poll.yes_votes = a_poll.yes_votes.checked_sub(old_tally)?,
+        VoteOption::No => a_poll.no_votes = a_poll.no_votes.checked_sub(old_tally)?,
+        VoteOption::Abstain => {
+            a_poll.abstain_votes = a_poll.abstain_votes.checked_sub(old_tally)?
+        }
+    }
+    match vote {
+        VoteOption::Yes => a_poll.yes_votes += tally,
+        VoteOption::No => a_poll.no_votes += tally,
+        VoteOption::Abstain => a_poll.abstain_votes += tally,
+    }
+    let voter_info = &mut a_poll.voters[position].1;
+    voter_info.vote = vote;
+    voter_info.weight = weight;
+    voter_info.voted_at_height = env.block.height;
+    poll(deps.storage).save(poll_key, &a_poll)?;
+
+    for (locked_poll_id, locked_weight) in token_manager.locked_tokens.iter_mut() {
+        if *locked_poll_id == poll_id {
+            *locked_weight = weight;
+        }
+    }
+    bank(deps.storage).save(info.sender.as_str().as_bytes(), &token_manager)?;
+
+    state.active_voting_weight = state.active_voting_weight.checked_sub(old_tally)? + tally;
+    config(deps.storage).save(&state)?;
+
+    let r = Response {
+        submessages: vec![],
+        messages: vec![],
+        attributes: vec![
+            attr("action", "change_vote"),
+            attr("poll_id", &poll_id),
+            attr("weight", &tally),
+            attr("voter", &info.sender),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+/*
+ * Locks weight for a poll whose start height hasn't been reached yet, so it can't be
+ * withdrawn or voted elsewhere in the meantime. The reservation doesn't count in the tally
+ * until the voter activates it with a vote once the poll is open.
+ */
+pub fn reserve_for_poll(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: u64,
+    weight: Uint128,
+) -> Result<Response, ContractError> {
+    let poll_key = &poll_id.to_be_bytes();
+    let state = config_read(deps.storage).load()?;
+    let mut a_poll = match poll_read(deps.storage).may_load(poll_key)? {
+        Some(a_poll) => a_poll,
+        None => return Err(ContractError::PollNotExist {}),
+    };
+
+    if a_poll.status != PollStatus::InProgress {
+        return Err(ContractError::PollNotInProgress {});
+    }
+    if !matches!(a_poll.start_height, Some(start_height) if start_height > env.block.height) {
+        return Err(ContractError::PollAlreadyOpen {});
+    }
+    if a_poll.commit_reveal {
+        return Err(ContractError::PollRequiresCommitReveal {});
+    }
+    if a_poll
+        .reservations
+        .iter()
+        .any(|(voter, _)| voter == &info.sender)
+    {
+        return Err(ContractError::PollSenderVoted {});
+    }
+
+    let (mut token_manager, _) = check_vote_weight(
+        deps.storage,
+        &env,
+        &info.sender,
+        &a_poll,
+        &state,
+        weight,
+        None,
+    )?;
+    token_manager.locked_tokens.push((poll_id, weight));
+    bank(deps.storage).save(info.sender.as_str().as_bytes(), &token_manager)?;
+
+    a_poll.reservations.push((info.sender.clone(), weight));
+    poll(deps.storage).save(poll_key, &a_poll)?;
+
+    let r = Response {
+        submessages: vec![],
+        messages: vec![],
+        attributes: vec![
+            attr("action", "reserve_for_poll"),
+            attr("poll_id", &poll_id),
+            attr("weight", &weight),
+            attr("voter", &info.sender),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+/*
+ * Casts the sender's reserved weight as a vote once the poll has opened. The reservation's
+ * lock is replaced by the lock of the vote.
+ */
+pub fn activate_reservation(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: u64,
+    vote: VoteOption,
+) -> Result<Response, ContractError> {
+    let poll_key = &poll_id.to_be_bytes();
+    let mut state = config(deps.storage).load()?;
+    let mut a_poll = load_votable_poll(deps.storage, &env, &state, poll_id)?;
+
+    let position = match a_poll
+        .reservations
+        .iter()
+        .position(|(voter, _)| voter == &info.sender)
+    {
+        Some(position) => position,
+        None => return Err(ContractError::NoReservation {}),
+    };
+    let (_, weight) = a_poll.reservations.remove(position);
+
+    let key = info.sender.as_str().as_bytes();
+    let mut token_manager = bank_read(deps.storage).load(key)?;
+    token_manager
+        .locked_tokens
+        .retain(|(locked_poll_id, _)| *locked_poll_id != poll_id);
+    bank(deps.storage).save(key, &token_manager)?;
+
+    let tally = record_vote(
+        deps.storage,
+        &env,
+        &info.sender,
+        poll_id,
+        &mut a_poll,
+        &mut state,
+        vote,
+        weight,
+        None,
+    )?;
+    poll(deps.storage).save(poll_key, &a_poll)?;
+    config(deps.storage).save(&state)?;
+
+    let r = Response {
+        submessages: vec![],
+        messages: vec![],
+        attributes: vec![
+            attr("action", "vote_casted"),
+            attr("poll_id", &poll_id),
+            attr("weight", &tally),
+            attr("voter", &info.sender),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+/// The commitment a voter submits with CommitVote: the sha256 hash of `vote:weight:salt`,
+/// with the vote as it is displayed ("yes", "no" or "abstain") and the exact salt that will
+/// later be sent with RevealVote.
+pub fn vote_commitment(vote: VoteOption, weight: Uint128, salt: &str) -> Binary {
+    let preimage = format!("{}:{}:{}", vote, weight, salt);
+    Binary::from(Sha256::digest(preimage.as_bytes()).to_vec())
+}
+
+/*
+ * Commit phase of a commit-reveal poll: while the poll is open, a voter stores only the hash
+ * of their vote. Nothing is tallied or locked until the vote is revealed.
+ */
+pub fn commit_vote(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: u64,
+    commitment: Binary,
+) -> Result<Response, ContractError> {
+    let poll_key = &poll_id.to_be_bytes();
+    let mut a_poll = match poll_read(deps.storage).may_load(poll_key)? {
+        Some(a_poll) => a_poll,
+        None => return Err(ContractError::PollNotExist {}),
+    };
+
+    if a_poll.status != PollStatus::InProgress {
+        return Err(ContractError::PollNotInProgress {});
+    }
+    if !a_poll.commit_reveal {
+        return Err(ContractError::PollNotCommitReveal {});
+    }
+    if let Some(start_height) = a_poll.start_height {
+        if start_height > env.block.height {
+            return Err(ContractError::PoolVotingPeriodNotStarted { start_height });
+        }
+    }
+    if a_poll.end_height <= env.block.height {
+        return Err(ContractError::PollVotingPeriodExpired {
+            expire_height: a_poll.end_height,
+        });
+    }
+    if a_poll
+        .commitments
+        .iter()
+        .any(|(voter, _)| voter == &info.sender)
+    {
+        return Err(ContractError::PollSenderVoted {});
+    }
+
+    a_poll.commitments.push((info.sender.clone(), commitment));
+    poll(deps.storage).save(poll_key, &a_poll)?;
+
+    let r = Response {
+        submessages: vec![],
+        messages: vec![],
+        attributes: vec![
+            attr("action", "commit_vote"),
+            attr("poll_id", &poll_id),
+            attr("voter", &info.sender),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+/*
+ * Reveal phase of a commit-reveal poll: once the voting window has closed, and until the end
+ * of the reveal period, a voter reveals the vote behind their commitment and it is applied to
+ * the tally like a regular vote. The poll keeps the revealed weight locked until the reveal
+ * period is over. Commitments which are never revealed are not counted.
+ */
+pub fn reveal_vote(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: u64,
+    vote: VoteOption,
+    weight: Uint128,
+    salt: String,
+) -> Result<Response, ContractError> {
+    let poll_key = &poll_id.to_be_bytes();
+    let mut a_poll = match poll_read(deps.storage).may_load(poll_key)? {
+        Some(a_poll) => a_poll,
+        None => return Err(ContractError::PollNotExist {}),
+    };
+
+    if a_poll.status != PollStatus::InProgress {
+        return Err(ContractError::PollNotInProgress {});
+    }
+    if !a_poll.commit_reveal {
+        return Err(ContractError::PollNotCommitReveal {});
+    }
+    if a_poll.end_height > env.block.height {
+        return Err(ContractError::PollVotingPeriodNotExpired {
+            expire_height: a_poll.end_height,
+        });
+    }
+    let reveal_end_height = poll_closes_at(&a_poll);
+    if reveal_end_height <= env.block.height {
+        return Err(ContractError::RevealPeriodExpired { reveal_end_height });
+    }
+
+    let position = match a_poll
+        .commitments
+        .iter()
+        .position(|(voter, _)| voter == &info.sender)
+    {
+        Some(position) => position,
+        None => return Err(ContractError::NoVoteCommitment {}),
+    };
+    if a_poll.commitments[position].1 != vote_commitment(vote, weight, &salt) {
+        return Err(ContractError::RevealMismatch {});
+    }
+    a_poll.commitments.remove(position);
+
+    let mut state = config(deps.storage).load()?;
+    let tally = record_vote(
+        deps.storage,
+        &env,
+        &info.sender,
+        poll_id,
+        &mut a_poll,
+        &mut state,
+        vote,
+        weight,
+        None,
+    )?;
+    poll(deps.storage).save(poll_key, &a_poll)?;
+    config(deps.storage).save(&state)?;
+
+    let r = Response {
+        submessages: vec![],
+        messages: vec![],
+        attributes: vec![
+            attr("action", "reveal_vote"),
+            attr("poll_id", &poll_id),
+            attr("weight", &tally),
+            attr("voter", &info.sender),
+        ],
+        data: None,
+    };
+    Ok(r)
+}
+
+// pays out staked tokens, as a cw20 transfer when the contract stakes a cw20 token
+fn release_tokens(
+    state: &State,
+    to_address: &Addr,
+    amount: Uint128,
+    action: &str,
+) -> StdResult<Response> {
+    let cw20_token = match &state.cw20_token {
+        Some(cw20_token) => cw20_token,
+        None => {
+            return Ok(send_tokens(
+                to_address,
+                vec![coin(amount.u128(), &state.denom)],
+                action,
+            ))
+        }
+    };
+
+    Ok(Response {
+        submessages: vec![],
+        messages: vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: cw20_token.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: to_address.to_string(),
+                amount,
+            })?,
+            send: vec![],
+        })],
+        attributes: vec![attr("action", action), attr("to", to_address.clone())],
+        data: None,
+    })
+}
+
+fn send_tokens(to_address: &Addr, amount: Vec<Coin>, action: &str) -> Response {
+    let attributes = vec![attr("action", action), attr("to", to_address.clone())];
+
+    Response {
+        submessages: vec![],
+        messages: vec![CosmosMsg::Bank(BankMsg::Send {
+            to_address: to_address.to_string(),
+            amount,
+        })],
+        attributes,
+        data: None,
+    }
+}
+
+/*
+ * Deployments from before the contract version was recorded still have the original storage
+ * layout, which migrate_legacy_storage rewrites. A deployment that already records a version
+ * of this contract has the current layout.
+ */
+#[entry_point]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    match get_contract_version(deps.storage) {
+        Ok(version) if version.contract != CONTRACT_NAME => {
+            return Err(ContractError::CannotMigrate {
+                previous_contract: version.contract,
+            })
+        }
+        Ok(_) => {}
+        Err(_) => migrate_legacy_storage(deps.storage)?,
+    }
+
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response {
+        submessages: vec![],
+        messages: vec![],
+        attributes: vec![attr("action", "migrate"), attr("version", CONTRACT_VERSION)],
+        data: None,
+    })
+}
+
+/*
+ * The original layout kept each poll's voters and their string votes in two separate lists
+ * and no running tallies, token managers had no unbonding list, and the config only held the
+ * denom, owner, poll count and staked tokens. Polls and token managers are rewritten with the
+ * tallies recounted from the votes, and the active poll bucket and the config's active weight
+ * are rebuilt from the polls still in progress. Every other field added since gets its default.
+ */
+fn migrate_legacy_storage(storage: &mut dyn Storage) -> Result<(), ContractError> {
+    let legacy_polls = legacy_poll_read(storage)
+        .range(None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut active_voting_weight = Uint128::zero();
+    for (key, legacy) in legacy_polls {
+        let mut yes_votes = Uint128::zero();
+        let mut no_votes = Uint128::zero();
+        let mut voters = vec![];
+        for (voter, legacy_voter) in legacy.voters.into_iter().zip(legacy.voter_info) {
+            // the original tally counted every vote other than "yes" as a no
+            let vote = if legacy_voter.vote == "yes" {
+                yes_votes += legacy_voter.weight;
+                VoteOption::Yes
+            } else {
+                no_votes += legacy_voter.weight;
+                VoteOption::No
+            };
+            let voter_info = Voter {
+                vote,
+                weight: legacy_voter.weight,
+                comment: None,
+                voted_at_height: 0,
+            };
+            voters.push((voter, voter_info));
+        }
+        if legacy.status == PollStatus::InProgress {
+            active_poll(storage).save(&key, &true)?;
+            active_voting_weight += yes_votes + no_votes;
+        }
+
+        let a_poll = Poll {
+            creator: legacy.creator,
+            status: legacy.status,
+            quorum_percentage: legacy.quorum_percentage,
+            yes_votes,
+            no_votes,
+            abstain_votes: Uint128::zero(),
+            voters,
+            end_height: legacy.end_height,
+            start_height: legacy.start_height,
+            description: legacy.description,
+            parent_poll_id: None,
+            requires_poll: None,
+            max_self_stake_percentage: None,
+            commit_reveal: false,
+            commitments: vec![],
+            reveal_end_height: None,
+            max_total_weight: None,
+            rejected_reason: None,
+            reservations: vec![],
+            auto_extend: None,
+            extensions: 0,
+            min_vote_percentage: None,
+        };
+        poll(storage).save(&key, &a_poll)?;
+    }
+
+    let legacy_managers = legacy_bank_read(storage)
+        .range(None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (key, legacy) in legacy_managers {
+        let token_manager = TokenManager {
+            token_balance: legacy.token_balance,
+            locked_tokens: legacy.locked_tokens,
+            participated_polls: legacy.participated_polls,
+            unbonding: vec![],
+        };
+        bank(storage).save(&key, &token_manager)?;
+    }
+
+    let legacy = legacy_config_read(storage).load()?;
+    let state = State {
+        denom: legacy.denom,
+        owner: legacy.owner,
+        poll_count: legacy.poll_count,
+        staked_tokens: legacy.staked_tokens,
+        creation_paused: false,
+        reject_duplicate_descriptions: false,
+        active_voting_weight,
+        max_absolute_end_height: None,
+        default_poll_duration: *DEFAULT_END_HEIGHT_BLOCKS,
+        weight_unit: None,
+        min_stake_amount: Uint128::from(DEFAULT_MIN_STAKE_AMOUNT),
+        min_desc_length: DEFAULT_MIN_DESC_LENGTH,
+        max_desc_length: DEFAULT_MAX_DESC_LENGTH,
+        pending_owner: None,
+        unbonding_period: None,
+        pause_reason: None,
+        cw20_token: None,
+    };
+    config(storage).save(&state)?;
+    Ok(())
+}
+
+#[entry_point]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&config_read(deps.storage).load()?),
+        QueryMsg::TokenStake { address } => {
+            token_balance(deps, deps.api.addr_validate(address.as_str())?)
+        }
+        QueryMsg::Poll { poll_id } => query_poll(deps, poll_id),
+        QueryMsg::FullWithdrawHeight { address } => {
+            query_full_withdraw_height(deps, env, deps.api.addr_validate(address.as_str())?)
+        }
+        QueryMsg::CanWithdraw { address, amount } => {
+            query_can_withdraw(deps, env, deps.api.addr_validate(address.as_str())?, amount)
+        }
+        QueryMsg::OpenLockCount { address } => {
+            query_open_lock_count(deps, env, deps.api.addr_validate(address.as_str())?)
+        }
+        QueryMsg::VotesInRange {
+            poll_id,
+            from_height,
+            to_height,
+        } => query_votes_in_range(deps, poll_id, from_height, to_height),
+        QueryMsg::VotingOpen { poll_id } => query_voting_open(deps, env, poll_id),
+        QueryMsg::QuorumDenominator { poll_id } => query_quorum_denominator(deps, poll_id),
+        QueryMsg::DryRunEndPoll { poll_id } => query_dry_run_end_poll(deps, env, poll_id),
+        QueryMsg::ActivePollIds { limit } => query_active_poll_ids(deps, limit),
+        QueryMsg::ActiveVotingWeight {} => to_binary(&ActiveVotingWeightResponse {
+            total: config_read(deps.storage).load()?.active_voting_weight,
+        }),
+        QueryMsg::FreeVotingPower { address } => {
+            query_free_voting_power(deps, env, deps.api.addr_validate(address.as_str())?)
+        }
+        QueryMsg::ParticipationRate { poll_id } => query_participation_rate(deps, poll_id),
+        QueryMsg::SubPolls { parent_poll_id } => query_sub_polls(deps, parent_poll_id),
+        QueryMsg::TopStakers { limit } => query_top_stakers(deps, limit),
+        QueryMsg::StaleEntries { limit } => query_stale_entries(deps, limit),
+        QueryMsg::ClosestToQuorum {} => query_closest_to_quorum(deps),
+        QueryMsg::VoteValidity {
+            poll_id,
+            address,
+            weight,
+        } => query_vote_validity(
+            deps,
+            env,
+            poll_id,
+            deps.api.addr_validate(address.as_str())?,
+            weight,
+        ),
+        QueryMsg::Voter { poll_id, address } => {
+            query_voter(deps, poll_id, deps.api.addr_validate(address.as_str())?)
+        }
+        QueryMsg::PollSchedule { poll_id } => query_poll_schedule(deps, env, poll_id),
+        // staking only ever takes the single denom set at instantiate,
+        // or the cw20 token's address when the contract stakes a cw20 token
+        QueryMsg::AcceptedDenoms {} => {
+            let state = config_read(deps.storage).load()?;
+            let denom = match state.cw20_token {
+                Some(cw20_token) => cw20_token.to_string(),
+                None => state.denom,
+            };
+            to_binary(&AcceptedDenomsResponse {
+                denoms: vec![denom],
+            })
+        }
+        QueryMsg::CreatorRecord { creator } => {
+            query_creator_record(deps, deps.api.addr_validate(creator.as_str())?)
+        }
+        QueryMsg::Polls {
+            start_after,
+            limit,
+            status_filter,
+        } => query_polls(deps, start_after, limit, status_filter),
+        QueryMsg::PollSummaries { start_after, limit } => {
+            query_poll_summaries(deps, start_after, limit)
+        }
+        QueryMsg::VoterAlignment { address } => {
+            query_voter_alignment(deps, deps.api.addr_validate(address.as_str())?)
+        }
+        QueryMsg::WeightStats { poll_id } => query_weight_stats(deps, poll_id),
+        QueryMsg::WeightDistribution { poll_id, buckets } => {
+            query_weight_distribution(deps, poll_id, buckets)
+        }
+        QueryMsg::Templates {} => query_templates(deps),
+        QueryMsg::PauseStatus {} => query_pause_status(deps),
+        QueryMsg::ContractVersion {} => to_binary(&get_contract_version(deps.storage)?),
+        QueryMsg::PollWithMyVote { poll_id, address } => {
+            query_poll_with_my_vote(deps, poll_id, deps.api.addr_validate(address.as_str())?)
+        }
+    }
+}
+
+fn query_poll(deps: Deps, poll_id: u64) -> StdResult<Binary> {
+    let key = &poll_id.to_be_bytes();
+
+    let poll = match poll_read(deps.storage).may_load(key)? {
+        Some(poll) => Some(poll),
+        None => return Err(StdError::generic_err("Poll does not exist")),
+    }
+    .unwrap();
+
+    to_binary(&poll_response(poll))
+}
+
+// the poll together with the address' own vote in it, if it voted
+fn query_poll_with_my_vote(deps: Deps, poll_id: u64, address: Addr) -> StdResult<Binary> {
+    let a_poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(StdError::generic_err("Poll does not exist")),
+    };
+
+    let my_vote = a_poll
+        .voters
+        .iter()
+        .find(|(voter, _)| voter == &address)
+        .map(|(_, voter)| VoterResponse {
+            vote: voter.vote,
+            weight: voter.weight,
+            comment: voter.comment.clone(),
+        });
+    to_binary(&PollWithMyVoteResponse {
+        poll: poll_response(a_poll),
+        my_vote,
+    })
+}
+
+fn poll_response(poll: Poll) -> PollResponse {
+    PollResponse {
+        creator: poll.creator.to_string(),
+        status: poll.status,
+        quorum_percentage: poll.quorum_percentage,
+        end_height: Some(poll.end_height),
+        start_height: poll.start_height,
+        description: poll.description,
+        yes_votes: poll.yes_votes,
+        no_votes: poll.no_votes,
+        num_voters: poll.voters.len() as u64,
+    }
+}
+
+// all polls in ascending poll_id order, optionally only those with the given status. Poll ids
+// are sequential and polls are never removed, so the next page starts after the last id of the
+// previous one.
+fn query_polls(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+    status_filter: Option<PollStatus>,
+) -> StdResult<Binary> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = match start_after {
+        Some(id) => match id.checked_add(1) {
+            Some(next) => Some(next.to_be_bytes()),
+            // no poll id comes after the largest one
+            None => return to_binary(&PollsResponse { polls: vec![] }),
+        },
+        None => None,
+    };
+
+    let polls = poll_read(deps.storage)
+        .range(start.as_ref().map(|s| &s[..]), None, Order::Ascending)
+        .filter(|item| match (item, &status_filter) {
+            (Ok((_, a_poll)), Some(status)) => &a_poll.status == status,
+            _ => true,
+        })
+        .take(limit)
+        .map(|item| item.map(|(_, a_poll)| poll_response(a_poll)))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    to_binary(&PollsResponse { polls })
+}
+
+// count and total weight of the votes cast between from_height and to_height (inclusive)
+fn query_votes_in_range(
+    deps: Deps,
+    poll_id: u64,
+    from_height: u64,
+    to_height: u64,
+) -> StdResult<Binary> {
+    let a_poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(StdError::generic_err("Poll does not exist")),
+    };
+
+    let mut count = 0u32;
+    let mut total_weight = Uint128::zero();
+    for (_, voter) in &a_poll.voters {
+        if voter.voted_at_height >= from_height && voter.voted_at_height <= to_height {
+            count += 1;
+            total_weight += voter.weight;
+        }
+    }
+
+    to_binary(&VotesInRangeResponse {
+        count,
+        total_weight,
+    })
+}
+
+// a poll is open for voting while it is in progress, has started and has not reached its end height
+fn query_voting_open(deps: Deps, env: Env, poll_id: u64) -> StdResult<Binary> {
+    let a_poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(StdError::generic_err("Poll does not exist")),
+    };
+
+    let height = env.block.height;
+    let open = a_poll.status == PollStatus::InProgress
+        && !matches!(a_poll.start_height, Some(start) if start > height)
+        && height < a_poll.end_height;
+
+    to_binary(&VotingOpenResponse { open })
+}
+
+fn query_quorum_denominator(deps: Deps, poll_id: u64) -> StdResult<Binary> {
+    if poll_read(deps.storage)
+        .may_load(&poll_id.to_be_bytes())?
+        .is_none()
+    {
+        return Err(StdError::generic_err("Poll does not exist"));
+    }
+
+    let (basis, denominator) = quorum_denominator(deps)?;
+    to_binary(&QuorumDenominatorResponse {
+        basis: basis.to_string(),
+        denominator,
+    })
+}
+
+// what end_poll would produce right now, ignoring who calls it and whether the voting
+// period is over; nothing is saved and no tokens are unlocked
+fn query_dry_run_end_poll(deps: Deps, env: Env, poll_id: u64) -> StdResult<Binary> {
+    let a_poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(StdError::generic_err("Poll does not exist")),
+    };
+    if a_poll.status != PollStatus::InProgress {
+        return Err(StdError::generic_err("Poll is not in progress"));
+    }
+
+    let mut result = tally_poll(deps, &a_poll).map_err(|e| StdError::generic_err(e.to_string()))?;
+    // a poll end_poll would extend stays in progress rather than being rejected
+    let max_absolute_end_height = config_read(deps.storage).load()?.max_absolute_end_height;
+    if auto_extended_end_height(
+        deps.storage,
+        poll_id,
+        &a_poll,
+        &result,
+        max_absolute_end_height,
+        env.block.height,
+    )?
+    .is_some()
+    {
+        result.status = PollStatus::InProgress;
+        result.passed = false;
+        result.rejected_reason = None;
+    }
+    to_binary(&result)
+}
+
+// ids of the polls in progress, read from the active poll bucket rather than scanning polls
+fn query_active_poll_ids(deps: Deps, limit: Option<u32>) -> StdResult<Binary> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let poll_ids = active_poll_read(deps.storage)
+        .range(None, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(key, _)| poll_id_from_key(&key)))
+        .collect::<StdResult<Vec<_>>>()?;
+    to_binary(&ActivePollIdsResponse { poll_ids })
+}
+
+// a light projection of the polls in ascending poll_id order, starting after start_after
+fn query_poll_summaries(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = match start_after {
+        Some(id) => match id.checked_add(1) {
+            Some(next) => Some(next.to_be_bytes()),
+            // no poll id comes after the largest one
+            None => return to_binary(&PollSummariesResponse { polls: vec![] }),
+        },
+        None => None,
+    };
+
+    let polls = poll_read(deps.storage)
+        .range(start.as_ref().map(|s| &s[..]), None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, a_poll) = item?;
+            Ok(PollSummary {
+                poll_id: poll_id_from_key(&key),
+                description: a_poll.description,
+                status: a_poll.status,
+                yes_votes: a_poll.yes_votes,
+                no_votes: a_poll.no_votes,
+                end_height: a_poll.end_height,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    to_binary(&PollSummariesResponse { polls })
+}
+
+// the largest stakers by token balance, descending. The bank isn't ordered by balance, so this
+// scans every staker and keeps the best `limit` in a min-heap.
+fn query_top_stakers(deps: Deps, limit: Option<u32>) -> StdResult<Binary> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(TOP_STAKERS_MAX_LIMIT) as usize;
+
+    let mut heap = BinaryHeap::with_capacity(limit + 1);
+    for item in bank_read(deps.storage).range(None, None, Order::Ascending) {
+        let (key, token_manager) = item?;
+        if token_manager.token_balance.is_zero() {
+            continue;
+        }
+        // reverse the address so that equal balances keep the lowest address
+        heap.push(Reverse((
+            token_manager.token_balance,
+            Reverse(key),
+            token_manager.participated_polls.len() as u64,
+        )));
+        if heap.len() > limit {
+            heap.pop();
+        }
+    }
+
+    let stakers = heap
+        .into_sorted_vec()
+        .into_iter()
+        .map(|Reverse((token_balance, Reverse(key), polls_voted))| {
+            Ok(StakerInfo {
+                address: String::from_utf8(key)
+                    .map_err(|_| StdError::generic_err("invalid staker address"))?,
+                token_balance,
+                polls_voted,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    to_binary(&TopStakersResponse { stakers })
+}
+
+// bank entries which hold nothing anymore: no balance, no locks and nothing unbonding
+fn query_stale_entries(deps: Deps, limit: Option<u32>) -> StdResult<Binary> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let mut addresses = vec![];
+    for item in bank_read(deps.storage).range(None, None, Order::Ascending) {
+        if addresses.len() == limit {
+            break;
+        }
+        let (key, token_manager) = item?;
+        if token_manager.token_balance.is_zero()
+            && token_manager.locked_tokens.is_empty()
+            && token_manager.unbonding.is_empty()
+        {
+            addresses.push(
+                String::from_utf8(key)
+                    .map_err(|_| StdError::generic_err("invalid staker address"))?,
+            );
+        }
+    }
+
+    to_binary(&StaleEntriesResponse { addresses })
+}
+
+// the poll's voting window relative to the current block, counts are zero once passed
+fn query_poll_schedule(deps: Deps, env: Env, poll_id: u64) -> StdResult<Binary> {
+    let a_poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(StdError::generic_err("Poll does not exist")),
+    };
+
+    let current_height = env.block.height;
+    to_binary(&PollScheduleResponse {
+        start_height: a_poll.start_height,
+        end_height: a_poll.end_height,
+        current_height,
+        blocks_until_start: a_poll
+            .start_height
+            .map_or(0, |start| start.saturating_sub(current_height)),
+        blocks_until_end: a_poll.end_height.saturating_sub(current_height),
+    })
+}
+
+// how an address voted on a poll
+fn query_voter(deps: Deps, poll_id: u64, address: Addr) -> StdResult<Binary> {
+    let a_poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(StdError::generic_err("Poll does not exist")),
+    };
+
+    match a_poll.voters.iter().find(|(voter, _)| voter == &address) {
+        Some((_, voter)) => to_binary(&VoterResponse {
+            vote: voter.vote,
+            weight: voter.weight,
+            comment: voter.comment.clone(),
+        }),
+        None => Err(StdError::generic_err("Address has not voted in this poll")),
+    }
+}
+
+// sums the voter's yes and no weight over every poll in participated_polls, abstains only
+// count towards polls_voted
+fn query_voter_alignment(deps: Deps, address: Addr) -> StdResult<Binary> {
+    let token_manager = bank_read(deps.storage)
+        .may_load(address.as_str().as_bytes())?
+        .unwrap_or_default();
+
+    let mut res = VoterAlignmentResponse {
+        total_yes_weight: Uint128::zero(),
+        total_no_weight: Uint128::zero(),
+        polls_voted: 0,
+    };
+    for poll_id in &token_manager.participated_polls {
+        let a_poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+            Some(a_poll) => a_poll,
+            None => continue,
+        };
+        if let Some((_, voter)) = a_poll.voters.iter().find(|(voter, _)| voter == &address) {
+            match voter.vote {
+                VoteOption::Yes => res.total_yes_weight += voter.weight,
+                VoteOption::No => res.total_no_weight += voter.weight,
+                VoteOption::Abstain => {}
+            }
+            res.polls_voted += 1;
+        }
+    }
+    to_binary(&res)
+}
+
+// poll creation is the only operation which can be paused, so `paused` follows it
+fn query_pause_status(deps: Deps) -> StdResult<Binary> {
+    let state = config_read(deps.storage).load()?;
+    to_binary(&PauseStatusResponse {
+        paused: state.creation_paused,
+        creation_paused: state.creation_paused,
+        reason: state.pause_reason,
+    })
+}
+
+// every saved template, in name order
+fn query_templates(deps: Deps) -> StdResult<Binary> {
+    let templates = template_read(deps.storage)
+        .range(None, None, Order::Ascending)
+        .map(|item| {
+            let (key, params) = item?;
+            Ok(TemplateResponse {
+                name: String::from_utf8(key)
+                    .map_err(|_| StdError::generic_err("invalid template name"))?,
+                params,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    to_binary(&TemplatesResponse { templates })
+}
+
+// min, max, median and mean of the voters' weights in a poll. Only the first
+// WEIGHT_STATS_MAX_VOTERS voters are sorted, for larger polls the result is an approximation
+// over those.
+fn query_weight_stats(deps: Deps, poll_id: u64) -> StdResult<Binary> {
+    let a_poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(StdError::generic_err("Poll does not exist")),
+    };
+
+    let mut weights: Vec<Uint128> = a_poll
+        .voters
+        .iter()
+        .take(WEIGHT_STATS_MAX_VOTERS)
+        .map(|(_, voter)| voter.weight)
+        .collect();
+    weights.sort();
+
+    let count = weights.len();
+    let res = if count == 0 {
+        WeightStatsResponse {
+            min_weight: Uint128::zero(),
+            max_weight: Uint128::zero(),
+            median_weight: Uint128::zero(),
+            mean_weight: Uint128::zero(),
+            voters_counted: 0,
+        }
+    } else {
+        let median_weight = if count.is_multiple_of(2) {
+            (weights[count / 2 - 1] + weights[count / 2]).multiply_ratio(1u128, 2u128)
+        } else {
+            weights[count / 2]
+        };
+        let total: Uint128 = weights.iter().copied().sum();
+        WeightStatsResponse {
+            min_weight: weights[0],
+            max_weight: weights[count - 1],
+            median_weight,
+            mean_weight: total.multiply_ratio(1u128, count as u128),
+            voters_counted: count as u64,
+        }
+    };
+    to_binary(&res)
+}
+
+/*
+ * Sorts the poll's voters into buckets by weight. A bucket holds the weights up to and including
+ * its boundary and above the previous one; weights above the last boundary get a final bucket
+ * without an upper bound.
+ */
+fn query_weight_distribution(deps: Deps, poll_id: u64, buckets: Vec<Uint128>) -> StdResult<Binary> {
+    if buckets.windows(2).any(|pair| pair[0] >= pair[1]) {
+        return Err(StdError::generic_err("Buckets must be sorted ascending"));
+    }
+    let a_poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(StdError::generic_err("Poll does not exist")),
+    };
+
+    let mut distribution: Vec<WeightBucket> = buckets
+        .iter()
+        .map(|boundary| Some(*boundary))
+        .chain(std::iter::once(None))
+        .map(|upper_bound| WeightBucket {
+            upper_bound,
+            voters: 0,
+            total_weight: Uint128::zero(),
+        })
+        .collect();
+    for (_, voter) in a_poll.voters.iter() {
+        let bucket =
+            &mut distribution[buckets.partition_point(|boundary| *boundary < voter.weight)];
+        bucket.voters += 1;
+        bucket.total_weight += voter.weight;
+    }
+
+    to_binary(&WeightDistributionResponse {
+        buckets: distribution,
+    })
+}
+
+// whether cast_vote would accept this vote right now, and if not the error it would return
+fn query_vote_validity(
+    deps: Deps,
+    env: Env,
+    poll_id: u64,
+    address: Addr,
+    weight: Uint128,
+) -> StdResult<Binary> {
+    let state = config_read(deps.storage).load()?;
+    let checked = load_votable_poll(deps.storage, &env, &state, poll_id).and_then(|a_poll| {
+        check_vote_weight(deps.storage, &env, &address, &a_poll, &state, weight, None)
+    });
+
+    let reason = checked.err().map(|e| e.to_string());
+    to_binary(&VoteValidityResponse {
+        valid: reason.is_none(),
+        reason,
+    })
+}
+
+// the open poll which needs the least additional weight to reach its quorum. Polls without a
+// quorum, or which already reached it, are skipped.
+fn query_closest_to_quorum(deps: Deps) -> StdResult<Binary> {
+    let state = config_read(deps.storage).load()?;
+    let (_, staked_weight) = quorum_denominator(deps)?;
+    let staked_weight = to_weight_units(staked_weight, state.weight_unit);
+
+    let mut closest: Option<(u64, Uint128)> = None;
+    for poll_id in active_poll_ids(deps.storage)? {
+        let a_poll = poll_read(deps.storage).load(&poll_id.to_be_bytes())?;
+        let quorum_percentage = match a_poll.quorum_percentage {
+            Some(quorum_percentage) => quorum_percentage,
+            None => continue,
+        };
+
+        // round up, tally_poll accepts tallied_weight * 100 >= quorum_percentage * staked_weight
+        let required = Uint128::from(quorum_percentage as u128).checked_mul(staked_weight)?;
+        let required = Uint128::from(required.u128().div_ceil(100));
+        let tallied_weight = a_poll.yes_votes + a_poll.no_votes + a_poll.abstain_votes;
+        let shortfall = required.saturating_sub(tallied_weight);
+        if shortfall.is_zero() {
+            continue;
+        }
+        if closest.is_none_or(|(_, best)| shortfall < best) {
+            closest = Some((poll_id, shortfall));
+        }
+    }
+
+    to_binary(&ClosestToQuorumResponse {
+        poll_id: closest.map(|(poll_id, _)| poll_id),
+        shortfall: closest.map(|(_, shortfall)| shortfall).unwrap_or_default(),
+    })
+}
+
+// how the polls created by an address have turned out. Polls still in progress only count
+// towards total_created.
+fn query_creator_record(deps: Deps, creator: Addr) -> StdResult<Binary> {
+    let mut record = CreatorRecordResponse {
+        total_created: 0,
+        passed: 0,
+        rejected: 0,
+        cancelled: 0,
+    };
+    for item in poll_read(deps.storage).range(None, None, Order::Ascending) {
+        let (_, a_poll) = item?;
+        if a_poll.creator != creator {
+            continue;
+        }
+        record.total_created += 1;
+        match a_poll.status {
+            PollStatus::Passed => record.passed += 1,
+            PollStatus::Rejected | PollStatus::Tied => record.rejected += 1,
+            PollStatus::Cancelled => record.cancelled += 1,
+            PollStatus::InProgress | PollStatus::Tally => {}
+        }
+    }
+
+    to_binary(&record)
+}
+
+// the amendments attached to a poll
+fn query_sub_polls(deps: Deps, parent_poll_id: u64) -> StdResult<Binary> {
+    let mut polls = vec![];
+    for item in poll_read(deps.storage).range(None, None, Order::Ascending) {
+        let (key, a_poll) = item?;
+        if a_poll.parent_poll_id == Some(parent_poll_id) {
+            polls.push(PollSummary {
+                poll_id: poll_id_from_key(&key),
+                description: a_poll.description,
+                status: a_poll.status,
+                yes_votes: a_poll.yes_votes,
+                no_votes: a_poll.no_votes,
+                end_height: a_poll.end_height,
+            });
+        }
+    }
+
+    to_binary(&SubPollsResponse { polls })
+}
+
+// poll keys are the big endian bytes of the poll_id
+fn poll_id_from_key(key: &[u8]) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(key);
+    u64::from_be_bytes(bytes)
+}
+
+// voting power not yet committed to any open poll
+fn query_free_voting_power(deps: Deps, env: Env, address: Addr) -> StdResult<Binary> {
+    let token_manager = bank_read(deps.storage)
+        .may_load(address.as_str().as_bytes())?
+        .unwrap_or_default();
+
+    let locked = open_locked_sum(deps.storage, &token_manager, env.block.height)?;
+    let free = token_manager.token_balance.saturating_sub(locked);
+    to_binary(&FreeVotingPowerResponse { free })
+}
+
+// share of state.staked_tokens which voted on the poll, independent of the quorum basis
+fn query_participation_rate(deps: Deps, poll_id: u64) -> StdResult<Binary> {
+    let a_poll = match poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+        Some(a_poll) => a_poll,
+        None => return Err(StdError::generic_err("Poll does not exist")),
+    };
+    let state = config_read(deps.storage).load()?;
+    let staked_tokens = state.staked_tokens;
+
+    // the tally is kept in weight units, so compare it against the stake in the same units
+    let tallied_weight = a_poll.yes_votes + a_poll.no_votes + a_poll.abstain_votes;
+    let staked_units = to_weight_units(staked_tokens, state.weight_unit);
+    let participation_percentage = if staked_units.is_zero() {
+        0
+    } else {
+        tallied_weight
+            .multiply_ratio(100u128, staked_units)
+            .u128()
+            .min(100) as u8
+    };
+
+    to_binary(&ParticipationRateResponse {
+        participation_percentage,
+        tallied_weight,
+        staked_tokens,
+    })
+}
+
+fn token_balance(deps: Deps, address: Addr) -> StdResult<Binary> {
+    let token_manager = bank_read(deps.storage)
+        .may_load(address.as_str().as_bytes())?
+        .unwrap_or_default();
+
+    let resp = TokenStakeResponse {
+        token_balance: token_manager.token_balance,
+    };
+
+    to_binary(&resp)
+}
+
+// the latest height among the address's locks in polls which still lock tokens and its
+// unbonding amounts which are not released yet, or None when nothing is held back
+fn query_full_withdraw_height(deps: Deps, env: Env, address: Addr) -> StdResult<Binary> {
+    let token_manager = bank_read(deps.storage)
+        .may_load(address.as_str().as_bytes())?
+        .unwrap_or_default();
+
+    let mut height: Option<u64> = None;
+    for (poll_id, _) in &token_manager.locked_tokens {
+        if let Some(a_poll) = poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+            if poll_locks_tokens(&a_poll, env.block.height) {
+                let closes_at = poll_closes_at(&a_poll);
+                height = Some(height.map_or(closes_at, |h| h.max(closes_at)));
+            }
+        }
+    }
+    for (release_height, _) in &token_manager.unbonding {
+        if *release_height > env.block.height {
+            height = Some(height.map_or(*release_height, |h| h.max(*release_height)));
+        }
+    }
+
+    to_binary(&FullWithdrawHeightResponse { height })
+}
+
+// same check as withdraw_voting_tokens' ExcessiveWithdraw, without withdrawing anything
+fn query_can_withdraw(deps: Deps, env: Env, address: Addr, amount: Uint128) -> StdResult<Binary> {
+    let key = address.as_str().as_bytes();
+
+    // with an unbonding period nothing can be withdrawn directly
+    let unbonding_period = config_read(deps.storage).load()?.unbonding_period;
+    let resp = match bank_read(deps.storage).may_load(key)? {
+        Some(token_manager) if unbonding_period.is_none() => {
+            let locked = open_locked_sum(deps.storage, &token_manager, env.block.height)?;
+            let max_withdrawable = token_manager.token_balance.saturating_sub(locked);
+            CanWithdrawResponse {
+                can_withdraw: amount <= max_withdrawable,
+                max_withdrawable,
+            }
+        }
+        _ => CanWithdrawResponse {
+            can_withdraw: false,
+            max_withdrawable: Uint128::zero(),
+        },
+    };
+
+    to_binary(&resp)
+}
+
+// counts the address's locks which reference polls that still lock tokens
+fn query_open_lock_count(deps: Deps, env: Env, address: Addr) -> StdResult<Binary> {
+    let token_manager = bank_read(deps.storage)
+        .may_load(address.as_str().as_bytes())?
+        .unwrap_or_default();
+
+    let mut open_poll_count = 0u32;
+    let mut total_locked_in_open = Uint128::zero();
+    for (poll_id, weight) in &token_manager.locked_tokens {
+        if let Some(a_poll) = poll_read(deps.storage).may_load(&poll_id.to_be_bytes())? {
+            if poll_locks_tokens(&a_poll, env.block.height) {
+                open_poll_count += 1;
+                total_locked_in_open += *weight;
+            }
+        }
+    }
+
+    to_binary(&OpenLockCountResponse {
+        open_poll_count,
+        total_locked_in_open,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{LegacyPoll, LegacyTokenManager, LegacyVoter};
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MOCK_CONTRACT_ADDR,
+    };
+    use cosmwasm_std::{coins, from_binary, from_slice, to_vec, MemoryStorage, OwnedDeps};
+    use cosmwasm_storage::to_length_prefixed;
+    use cw2::ContractVersion;
+
+    const TEST_CREATOR: &str = "creator";
+    const TEST_VOTER: &str = "voter1";
+
+    fn default_instantiate_msg() -> InstantiateMsg {
+        InstantiateMsg {
+            denom: String::from(VOTING_TOKEN),
+            reject_duplicate_descriptions: None,
+            max_absolute_end_height: None,
+            weight_unit: None,
+            min_stake_amount: None,
+            min_desc_length: None,
+            max_desc_length: None,
+            unbonding_period: None,
+            cw20_token: None,
+        }
+    }
+
+    // the fields of ExecuteMsg::CreatePoll, so a test only spells out the ones it sets
+    struct CreatePollMsg {
+        quorum_percentage: Option<u8>,
+        description: String,
+        start_height: Option<u64>,
+        end_height: Option<u64>,
+        parent_poll_id: Option<u64>,
+        requires_poll: Option<u64>,
+        max_self_stake_percentage: Option<u8>,
+        commit_reveal: Option<bool>,
+        max_total_weight: Option<Uint128>,
+        auto_extend: Option<AutoExtend>,
+        min_vote_percentage: Option<u8>,
+    }
+
+    impl From<CreatePollMsg> for ExecuteMsg {
+        fn from(msg: CreatePollMsg) -> Self {
+            ExecuteMsg::CreatePoll {
+                quorum_percentage: msg.quorum_percentage,
+                description: msg.description,
+                start_height: msg.start_height,
+                end_height: msg.end_height,
+                parent_poll_id: msg.parent_poll_id,
+                requires_poll: msg.requires_poll,
+                max_self_stake_percentage: msg.max_self_stake_percentage,
+                commit_reveal: msg.commit_reveal,
+                max_total_weight: msg.max_total_weight,
+                auto_extend: msg.auto_extend,
+                min_vote_percentage: msg.min_vote_percentage,
+            }
+        }
+    }
+
+    fn create_poll_params(description: &str) -> CreatePollMsg {
+        CreatePollMsg {
+            quorum_percentage: None,
+            description: description.to_string(),
+            start_height: None,
+            end_height: None,
+            parent_poll_id: None,
+            requires_poll: None,
+            max_self_stake_percentage: None,
+            commit_reveal: None,
+            max_total_weight: None,
+            auto_extend: None,
+            min_vote_percentage: None,
+        }
+    }
+
+    fn mock_instantiate() -> OwnedDeps<MemoryStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies(&[]);
+        let msg = default_instantiate_msg();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        deps
+    }
+
+    fn create_poll_msg(description: &str) -> ExecuteMsg {
+        create_poll_params(description).into()
+    }
+
+    fn stake(deps: DepsMut, sender: &str, amount: u128) {
+        let info = mock_info(sender, &coins(amount, VOTING_TOKEN));
+        let _ = execute(deps, mock_env(), info, ExecuteMsg::StakeVotingTokens {}).unwrap();
+    }
+
+    fn vote_msg(poll_id: u64, vote: &str, weight: u128) -> ExecuteMsg {
+        ExecuteMsg::CastVote {
+            poll_id,
+            vote: vote.parse().unwrap(),
+            weight: Uint128::from(weight),
+            comment: None,
+        }
+    }
+
+    #[test]
+    fn instantiate_rejects_invalid_denom() {
+        for denom in ["", "1token", "to", "voting token"].iter() {
+            let mut deps = mock_dependencies(&[]);
+            let msg = InstantiateMsg {
+                denom: denom.to_string(),
+                ..default_instantiate_msg()
+            };
+            let info = mock_info(TEST_CREATOR, &[]);
+            let err = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+            match err {
+                ContractError::InvalidDenom { denom: d } => assert_eq!(&d, denom),
+                e => panic!("unexpected error: {}", e),
+            }
+        }
+
+        // ibc denoms are fine
+        let mut deps = mock_dependencies(&[]);
+        let msg = InstantiateMsg {
+            denom: String::from(
+                "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2",
+            ),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    #[test]
+    fn desc_length_bounds_must_be_ordered() {
+        let mut deps = mock_dependencies(&[]);
+        let msg = InstantiateMsg {
+            min_desc_length: Some(10),
+            max_desc_length: Some(5),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let err = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::InvalidDescLengths { .. } => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // a single bound is checked against the one already configured
+        let mut deps = mock_instantiate();
+        let msg = ExecuteMsg::UpdateConfig {
+            default_poll_duration: None,
+            reject_duplicate_descriptions: None,
+            max_absolute_end_height: None,
+            clear_max_absolute_end_height: None,
+            denom: None,
+            min_stake_amount: None,
+            min_desc_length: Some(DEFAULT_MAX_DESC_LENGTH + 1),
+            max_desc_length: None,
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::InvalidDescLengths {
+                min_desc_length,
+                max_desc_length,
+            } => {
+                assert_eq!(min_desc_length, DEFAULT_MAX_DESC_LENGTH + 1);
+                assert_eq!(max_desc_length, DEFAULT_MAX_DESC_LENGTH);
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn cast_vote_with_comment() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 10);
+
+        // a comment over the limit is rejected
+        let msg = ExecuteMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Uint128::from(5u128),
+            comment: Some("a".repeat(257)),
+        };
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::CommentTooLong { max_comment_length } => {
+                assert_eq!(max_comment_length, MAX_COMMENT_LENGTH)
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let msg = ExecuteMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Uint128::from(5u128),
+            comment: Some("because".to_string()),
+        };
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // the comment is returned alongside the vote
+        let msg = QueryMsg::Voter {
+            poll_id: 1,
+            address: TEST_VOTER.to_string(),
+        };
+        let res: VoterResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.comment, Some("because".to_string()));
+    }
+
+    #[test]
+    fn full_withdraw_height_tracks_open_poll_locks() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), TEST_VOTER, 10);
+
+        // nothing locked yet, so everything can be withdrawn now
+        let msg = QueryMsg::FullWithdrawHeight {
+            address: TEST_VOTER.to_string(),
+        };
+        let res: FullWithdrawHeightResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg.clone()).unwrap()).unwrap();
+        assert_eq!(res.height, None);
+
+        let end_height = mock_env().block.height + 1000;
+        let poll_msg: ExecuteMsg = CreatePollMsg {
+            end_height: Some(end_height),
+            ..create_poll_params("test")
+        }
+        .into();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, poll_msg).unwrap();
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 5)).unwrap();
+
+        let res: FullWithdrawHeightResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.height, Some(end_height));
+    }
+
+    #[test]
+    fn creation_paused_blocks_only_create_poll() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 10);
+
+        // only the owner can pause
+        let msg = ExecuteMsg::SetCreationPaused {
+            paused: true,
+            reason: Some("migration in progress".to_string()),
+        };
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res: PauseStatusResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::PauseStatus {}).unwrap())
+                .unwrap();
+        assert_eq!(
+            res,
+            PauseStatusResponse {
+                paused: true,
+                creation_paused: true,
+                reason: Some("migration in progress".to_string()),
+            }
+        );
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, create_poll_msg("other")).unwrap_err();
+        match err {
+            ContractError::CreationPaused {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // voting on the existing poll still works
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 5)).unwrap();
+
+        // and poll creation resumes once unpaused
+        let msg = ExecuteMsg::SetCreationPaused {
+            paused: false,
+            reason: None,
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.pause_reason, None);
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("other")).unwrap();
+    }
+
+    #[test]
+    fn ownership_transfer_needs_acceptance() {
+        let mut deps = mock_instantiate();
+        let msg = ExecuteMsg::TransferOwnership {
+            new_owner: "new_owner".to_string(),
+        };
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let res: State =
+            from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+        assert_eq!(res.owner, Addr::unchecked(TEST_CREATOR));
+        assert_eq!(res.pending_owner, Some(Addr::unchecked("new_owner")));
+
+        // only the pending owner can accept
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::AcceptOwnership {},
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let info = mock_info("new_owner", &[]);
+        let _ = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::AcceptOwnership {},
+        )
+        .unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.owner, Addr::unchecked("new_owner"));
+        assert_eq!(state.pending_owner, None);
+
+        // the old owner no longer has owner rights
+        let info = mock_info(TEST_CREATOR, &[]);
+        let msg = ExecuteMsg::SetCreationPaused {
+            paused: true,
+            reason: None,
+        };
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    }
+
+    #[test]
+    fn create_poll_from_template() {
+        let mut deps = mock_instantiate();
+        let params = CreatePollParams {
+            quorum_percentage: Some(20),
+            duration: Some(1_000),
+            max_self_stake_percentage: Some(50),
+            commit_reveal: false,
+            max_total_weight: Some(Uint128::from(500u128)),
+        };
+        let msg = ExecuteMsg::SaveTemplate {
+            name: "budget".to_string(),
+            params: params.clone(),
+        };
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res: TemplatesResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Templates {}).unwrap())
+                .unwrap();
+        assert_eq!(
+            res.templates,
+            vec![TemplateResponse {
+                name: "budget".to_string(),
+                params,
+            }]
+        );
+
+        let msg = ExecuteMsg::CreateFromTemplate {
+            name: "budget".to_string(),
+            description: "march budget".to_string(),
+        };
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.description, "march budget");
+        assert_eq!(a_poll.creator, Addr::unchecked(TEST_VOTER));
+        assert_eq!(a_poll.quorum_percentage, Some(20));
+        assert_eq!(a_poll.end_height, mock_env().block.height + 1_000);
+        assert_eq!(a_poll.max_self_stake_percentage, Some(50));
+        assert_eq!(a_poll.max_total_weight, Some(Uint128::from(500u128)));
+
+        let msg = ExecuteMsg::CreateFromTemplate {
+            name: "missing".to_string(),
+            description: "march budget".to_string(),
+        };
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::TemplateNotFound { name } => assert_eq!(name, "missing"),
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn cast_vote_updates_running_tally() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        stake(deps.as_mut(), "voter1", 10);
+        stake(deps.as_mut(), "voter2", 10);
+        stake(deps.as_mut(), "voter3", 10);
+
+        let info = mock_info("voter1", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 7)).unwrap();
+        let info = mock_info("voter2", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "no", 4)).unwrap();
+        let info = mock_info("voter3", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 2)).unwrap();
+
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.yes_votes, Uint128::from(9u128));
+        assert_eq!(a_poll.no_votes, Uint128::from(4u128));
+    }
+
+    #[test]
+    fn can_withdraw_respects_locked_tokens() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 10);
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 6)).unwrap();
+
+        let msg = QueryMsg::CanWithdraw {
+            address: TEST_VOTER.to_string(),
+            amount: Uint128::from(5u128),
+        };
+        let res: CanWithdrawResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert!(!res.can_withdraw);
+        assert_eq!(res.max_withdrawable, Uint128::from(4u128));
+
+        let msg = QueryMsg::CanWithdraw {
+            address: TEST_VOTER.to_string(),
+            amount: Uint128::from(4u128),
+        };
+        let res: CanWithdrawResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert!(res.can_withdraw);
+    }
+
+    #[test]
+    fn full_withdraw_removes_bank_entry() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), TEST_VOTER, 10);
+        stake(deps.as_mut(), "voter2", 5);
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let msg = ExecuteMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(4u128)),
+        };
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert!(bank_read(&deps.storage)
+            .may_load(TEST_VOTER.as_bytes())
+            .unwrap()
+            .is_some());
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let msg = ExecuteMsg::WithdrawVotingTokens { amount: None };
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            bank_read(&deps.storage)
+                .may_load(TEST_VOTER.as_bytes())
+                .unwrap(),
+            None
+        );
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.staked_tokens, Uint128::from(5u128));
+    }
+
+    #[test]
+    fn unbonded_tokens_claimable_after_unbonding_period() {
+        let mut deps = mock_dependencies(&[]);
+        let msg = InstantiateMsg {
+            unbonding_period: Some(100),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 50);
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let msg = ExecuteMsg::WithdrawVotingTokens { amount: None };
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::UnbondingRequired { unbonding_period } => {
+                assert_eq!(unbonding_period, 100)
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // the queries agree that nothing can be withdrawn directly
+        let msg = QueryMsg::CanWithdraw {
+            address: TEST_VOTER.to_string(),
+            amount: Uint128::from(10u128),
+        };
+        let res: CanWithdrawResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert!(!res.can_withdraw);
+        assert_eq!(res.max_withdrawable, Uint128::zero());
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let msg = ExecuteMsg::Unbond {
+            amount: Some(Uint128::from(30u128)),
+        };
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.staked_tokens, Uint128::from(20u128));
+
+        let msg = QueryMsg::FullWithdrawHeight {
+            address: TEST_VOTER.to_string(),
+        };
+        let res: FullWithdrawHeightResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.height, Some(mock_env().block.height + 100));
+
+        let mut env = mock_env();
+        env.block.height += 99;
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), env, info, ExecuteMsg::ClaimUnbonded {}).unwrap_err();
+        match err {
+            ContractError::NoMaturedUnbonding {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let mut env = mock_env();
+        env.block.height += 100;
+        let info = mock_info(TEST_VOTER, &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::ClaimUnbonded {}).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                to_address: TEST_VOTER.to_string(),
+                amount: coins(30, VOTING_TOKEN),
+            })]
+        );
+        let token_manager = bank_read(&deps.storage)
+            .load(TEST_VOTER.as_bytes())
+            .unwrap();
+        assert_eq!(token_manager.token_balance, Uint128::from(20u128));
+        assert!(token_manager.unbonding.is_empty());
+    }
+
+    #[test]
+    fn update_config_rejects_denom_change_while_unbonding() {
+        let mut deps = mock_dependencies(&[]);
+        let msg = InstantiateMsg {
+            unbonding_period: Some(100),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 50);
+
+        // nothing is staked any more, but the unbonding amount is still owed in the denom
+        let info = mock_info(TEST_VOTER, &[]);
+        let msg = ExecuteMsg::Unbond { amount: None };
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert!(state.staked_tokens.is_zero());
+
+        let msg = ExecuteMsg::UpdateConfig {
+            default_poll_duration: None,
+            reject_duplicate_descriptions: None,
+            max_absolute_end_height: None,
+            clear_max_absolute_end_height: None,
+            denom: Some("other_token".to_string()),
+            min_stake_amount: None,
+            min_desc_length: None,
+            max_desc_length: None,
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::DenomChangeWithStake {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn instantiate_sets_contract_version() {
+        let deps = mock_instantiate();
+        let msg = QueryMsg::ContractVersion {};
+        let version: ContractVersion =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(version.contract, CONTRACT_NAME);
+        assert_eq!(version.version, CONTRACT_VERSION);
+    }
+
+    #[test]
+    fn migrate_rewrites_legacy_storage() {
+        let mut deps = mock_dependencies(&[]);
+        let in_progress_end = mock_env().block.height + 10;
+        deps.storage.set(
+            &to_length_prefixed(b"config"),
+            br#"{"denom":"voting_token","owner":"creator","poll_count":2,"staked_tokens":"100"}"#,
+        );
+        let legacy_poll =
+            |status: PollStatus, votes: &[(&str, &str, u128)], end_height: u64| LegacyPoll {
+                creator: Addr::unchecked(TEST_CREATOR),
+                status,
+                quorum_percentage: None,
+                yes_votes: Uint128::zero(),
+                no_votes: Uint128::zero(),
+                voters: votes
+                    .iter()
+                    .map(|(voter, _, _)| Addr::unchecked(*voter))
+                    .collect(),
+                voter_info: votes
+                    .iter()
+                    .map(|(_, vote, weight)| LegacyVoter {
+                        vote: vote.to_string(),
+                        weight: Uint128::from(*weight),
+                    })
+                    .collect(),
+                end_height,
+                start_height: None,
+                description: "legacy".to_string(),
+            };
+        let polls = [
+            legacy_poll(PollStatus::Passed, &[("voter1", "yes", 10)], 100),
+            legacy_poll(
+                PollStatus::InProgress,
+                &[("voter1", "yes", 30), ("voter2", "no", 20)],
+                in_progress_end,
+            ),
+        ];
+        for (poll_id, legacy) in (1u64..).zip(polls.iter()) {
+            let key = [to_length_prefixed(b"polls"), poll_id.to_be_bytes().to_vec()].concat();
+            deps.storage.set(&key, &to_vec(legacy).unwrap());
+        }
+        for (voter, balance, lock) in [("voter1", 80u128, 30u128), ("voter2", 20, 20)].iter() {
+            let legacy = LegacyTokenManager {
+                token_balance: Uint128::from(*balance),
+                locked_tokens: vec![(2, Uint128::from(*lock))],
+                participated_polls: vec![1, 2],
+            };
+            let key = [to_length_prefixed(b"bank"), voter.as_bytes().to_vec()].concat();
+            deps.storage.set(&key, &to_vec(&legacy).unwrap());
+        }
+
+        let _ = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.denom, VOTING_TOKEN);
+        assert_eq!(state.owner, Addr::unchecked(TEST_CREATOR));
+        assert_eq!(state.poll_count, 2);
+        assert_eq!(state.staked_tokens, Uint128::from(100u128));
+        assert_eq!(active_poll_ids(&deps.storage).unwrap(), vec![2]);
+        assert_eq!(state.active_voting_weight, Uint128::from(50u128));
+        assert_eq!(
+            state.min_stake_amount,
+            Uint128::from(DEFAULT_MIN_STAKE_AMOUNT)
+        );
+        assert_eq!(state.max_desc_length, DEFAULT_MAX_DESC_LENGTH);
+        assert_eq!(state.default_poll_duration, *DEFAULT_END_HEIGHT_BLOCKS);
+        assert_eq!(state.cw20_token, None);
+        let version = get_contract_version(&deps.storage).unwrap();
+        assert_eq!(version.version, CONTRACT_VERSION);
+
+        let a_poll = poll_read(&deps.storage).load(&2u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.yes_votes, Uint128::from(30u128));
+        assert_eq!(a_poll.no_votes, Uint128::from(20u128));
+        assert_eq!(a_poll.voters[1].0, Addr::unchecked("voter2"));
+        assert_eq!(a_poll.voters[1].1.vote, VoteOption::No);
+        let token_manager = bank_read(&deps.storage).load(b"voter1").unwrap();
+        assert_eq!(token_manager.token_balance, Uint128::from(80u128));
+        assert!(token_manager.unbonding.is_empty());
+
+        // already versioned, the stored config is kept as it is
+        let _ = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        assert_eq!(config_read(&deps.storage).load().unwrap(), state);
+
+        // the migrated poll can still be ended, releasing its locks
+        let mut env = mock_env();
+        env.block.height = in_progress_end;
+        let info = mock_info(TEST_CREATOR, &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::EndPoll { poll_id: 2 }).unwrap();
+        let result: PollResult = from_binary(&res.data.unwrap()).unwrap();
+        assert!(result.passed);
+        let state = config_read(&deps.storage).load().unwrap();
+        assert!(active_poll_ids(&deps.storage).unwrap().is_empty());
+        assert_eq!(state.active_voting_weight, Uint128::zero());
+        let token_manager = bank_read(&deps.storage).load(b"voter2").unwrap();
+        assert!(token_manager.locked_tokens.is_empty());
+    }
+
+    #[test]
+    fn migrate_rejects_other_contract() {
+        let mut deps = mock_dependencies(&[]);
+        set_contract_version(&mut deps.storage, "crates.io:cw-other", "0.1.0").unwrap();
+        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+        match err {
+            ContractError::CannotMigrate { previous_contract } => {
+                assert_eq!(previous_contract, "crates.io:cw-other")
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn stake_rejects_overflowing_total() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), TEST_VOTER, u128::MAX - 10);
+
+        let info = mock_info("voter2", &coins(20, VOTING_TOKEN));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::StakeVotingTokens {},
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Overflow(_) => {}
+            e => panic!("unexpected error: {}", e),
+        }
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.staked_tokens, Uint128::from(u128::MAX - 10));
+    }
+
+    #[test]
+    fn cw20_tokens_staked_through_receive() {
+        let mut deps = mock_dependencies(&[]);
+        let msg = InstantiateMsg {
+            cw20_token: Some(String::from("voting_cw20")),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info(TEST_VOTER, &coins(50, VOTING_TOKEN));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::StakeVotingTokens {},
+        )
+        .unwrap_err();
+        match err {
+            ContractError::UnsupportedToken {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let receive_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: TEST_VOTER.to_string(),
+            amount: Uint128::from(50u128),
+            msg: to_binary(&Cw20HookMsg::StakeVotingTokens {}).unwrap(),
+        });
+        let info = mock_info("other_cw20", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, receive_msg.clone()).unwrap_err();
+        match err {
+            ContractError::UnsupportedToken {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let info = mock_info("voting_cw20", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, receive_msg).unwrap();
+        let token_manager = bank_read(&deps.storage)
+            .load(TEST_VOTER.as_bytes())
+            .unwrap();
+        assert_eq!(token_manager.token_balance, Uint128::from(50u128));
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.staked_tokens, Uint128::from(50u128));
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let msg = ExecuteMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(20u128)),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: String::from("voting_cw20"),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: TEST_VOTER.to_string(),
+                    amount: Uint128::from(20u128),
+                })
+                .unwrap(),
+                send: vec![],
+            })]
+        );
+    }
+
+    #[test]
+    fn locks_in_all_open_polls_hold_stake_back() {
+        let mut deps = mock_instantiate();
+        for description in ["first", "second"].iter() {
+            let info = mock_info(TEST_CREATOR, &[]);
+            let _ = execute(
+                deps.as_mut(),
+                mock_env(),
+                info,
+                create_poll_msg(description),
+            )
+            .unwrap();
+        }
+        stake(deps.as_mut(), TEST_VOTER, 100);
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 60)).unwrap();
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(2, "yes", 40)).unwrap();
+
+        // 60 + 40 are locked, nothing is left to move to another voter
+        let msg = ExecuteMsg::TransferStake {
+            to: "voter2".to_string(),
+            amount: Uint128::from(40u128),
+        };
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::InsufficientFreeBalance { free_balance } => {
+                assert_eq!(free_balance, Uint128::zero())
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let msg = ExecuteMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(40u128)),
+        };
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::ExcessiveWithdraw { max_amount } => {
+                assert_eq!(max_amount, Uint128::zero())
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let msg = QueryMsg::CanWithdraw {
+            address: TEST_VOTER.to_string(),
+            amount: Uint128::from(40u128),
+        };
+        let res: CanWithdrawResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert!(!res.can_withdraw);
+        assert_eq!(res.max_withdrawable, Uint128::zero());
+    }
+
+    #[test]
+    fn transfer_stake_moves_only_free_balance() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 10);
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 6)).unwrap();
+
+        // the 6 voted tokens are locked, so only 4 can move
+        let msg = ExecuteMsg::TransferStake {
+            to: "voter2".to_string(),
+            amount: Uint128::from(5u128),
+        };
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::InsufficientFreeBalance { free_balance } => {
+                assert_eq!(free_balance, Uint128::from(4u128))
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let msg = ExecuteMsg::TransferStake {
+            to: "voter2".to_string(),
+            amount: Uint128::from(4u128),
+        };
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let sender = bank_read(&deps.storage).load(b"voter1").unwrap();
+        assert_eq!(sender.token_balance, Uint128::from(6u128));
+        let recipient = bank_read(&deps.storage).load(b"voter2").unwrap();
+        assert_eq!(recipient.token_balance, Uint128::from(4u128));
+
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.staked_tokens, Uint128::from(10u128));
+    }
+
+    #[test]
+    fn open_lock_count_ignores_ended_polls() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), TEST_VOTER, 10);
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(10, VOTING_TOKEN));
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("first")).unwrap();
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 4)).unwrap();
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("second")).unwrap();
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(2, "no", 3)).unwrap();
+
+        // end the first poll
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS;
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), env, info, ExecuteMsg::EndPoll { poll_id: 1 }).unwrap();
+
+        let msg = QueryMsg::OpenLockCount {
+            address: TEST_VOTER.to_string(),
+        };
+        let res: OpenLockCountResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.open_poll_count, 1);
+        assert_eq!(res.total_locked_in_open, Uint128::from(3u128));
+
+        // the second poll is not ended yet but stops locking at its end height
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS;
+        let msg = QueryMsg::OpenLockCount {
+            address: TEST_VOTER.to_string(),
+        };
+        let res: OpenLockCountResponse =
+            from_binary(&query(deps.as_ref(), env, msg).unwrap()).unwrap();
+        assert_eq!(res.open_poll_count, 0);
+        assert_eq!(res.total_locked_in_open, Uint128::zero());
+    }
+
+    #[test]
+    fn cast_vote_rejects_unknown_vote_options() {
+        // options are checked when the message is parsed, only the exact lowercase names match
+        for vote in ["Yes", "YES", "maybe"].iter() {
+            let raw = format!(
+                r#"{{"cast_vote":{{"poll_id":1,"vote":"{}","weight":"3","comment":null}}}}"#,
+                vote
+            );
+            assert!(from_slice::<ExecuteMsg>(raw.as_bytes()).is_err());
+            assert!(vote.parse::<VoteOption>().is_err());
+        }
+
+        let raw = br#"{"cast_vote":{"poll_id":1,"vote":"abstain","weight":"3","comment":null}}"#;
+        match from_slice::<ExecuteMsg>(raw).unwrap() {
+            ExecuteMsg::CastVote { vote, .. } => assert_eq!(vote, VoteOption::Abstain),
+            msg => panic!("unexpected message: {:?}", msg),
+        }
+
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 10);
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 3)).unwrap();
+
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.voters[0].1.vote, VoteOption::Yes);
+        assert_eq!(a_poll.voters[0].1.vote.to_string(), "yes");
+        assert_eq!(a_poll.yes_votes, Uint128::from(3u128));
+    }
+
+    #[test]
+    fn votes_in_range_counts_by_voted_height() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+
+        let start = mock_env().block.height;
+        for (i, voter) in ["voter1", "voter2", "voter3"].iter().enumerate() {
+            stake(deps.as_mut(), voter, 10);
+            let mut env = mock_env();
+            env.block.height = start + 10 * i as u64;
+            let info = mock_info(voter, &[]);
+            let _ = execute(deps.as_mut(), env, info, vote_msg(1, "yes", 1 + i as u128)).unwrap();
+        }
+
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.voters[2].1.voted_at_height, start + 20);
+
+        // only voter2 and voter3 fall in the range
+        let msg = QueryMsg::VotesInRange {
+            poll_id: 1,
+            from_height: start + 5,
+            to_height: start + 20,
+        };
+        let res: VotesInRangeResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.count, 2);
+        assert_eq!(res.total_weight, Uint128::from(5u128));
+    }
+
+    #[test]
+    fn merge_stake_combines_balances_and_locks() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        stake(deps.as_mut(), "old", 10);
+        stake(deps.as_mut(), "new", 5);
+
+        let info = mock_info("old", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 4)).unwrap();
+        let info = mock_info("new", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "no", 3)).unwrap();
+
+        let msg = ExecuteMsg::MergeStake {
+            from: "old".to_string(),
+            to: "new".to_string(),
+        };
+
+        // only the owner can merge
+        let info = mock_info("new", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let merged = bank_read(&deps.storage).load(b"new").unwrap();
+        assert_eq!(merged.token_balance, Uint128::from(15u128));
+        assert_eq!(merged.locked_tokens, vec![(1, Uint128::from(7u128))]);
+        assert_eq!(merged.participated_polls, vec![1]);
+        assert_eq!(bank_read(&deps.storage).may_load(b"old").unwrap(), None);
+
+        // both voted, so each keeps its own record
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        let voters: Vec<&str> = a_poll.voters.iter().map(|(v, _)| v.as_str()).collect();
+        assert_eq!(voters, vec!["old", "new"]);
+
+        // and ending the poll releases the merged lock
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(15, VOTING_TOKEN));
+        let _ = end_poll_result(deps.as_mut(), 1);
+        let merged = bank_read(&deps.storage).load(b"new").unwrap();
+        assert_eq!(merged.locked_tokens, vec![]);
+    }
+
+    #[test]
+    fn merge_stake_moves_reservations_and_commitments() {
+        let mut deps = mock_instantiate();
+        let start = mock_env().block.height + 10;
+        let msg: ExecuteMsg = CreatePollMsg {
+            start_height: Some(start),
+            end_height: Some(start + 50),
+            ..create_poll_params("reserved")
+        }
+        .into();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, commit_reveal_poll_msg()).unwrap();
+        stake(deps.as_mut(), "old", 10);
+
+        let msg = ExecuteMsg::ReserveForPoll {
+            poll_id: 1,
+            weight: Uint128::from(6u128),
+        };
+        let info = mock_info("old", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let commitment = vote_commitment(VoteOption::Yes, Uint128::from(4u128), "salt");
+        let msg = ExecuteMsg::CommitVote {
+            poll_id: 2,
+            commitment: commitment.clone(),
+        };
+        let info = mock_info("old", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // an address can't be merged into itself
+        let msg = ExecuteMsg::MergeStake {
+            from: "old".to_string(),
+            to: "old".to_string(),
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::MergeIntoSelf {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let msg = ExecuteMsg::MergeStake {
+            from: "old".to_string(),
+            to: "new".to_string(),
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(
+            a_poll.reservations,
+            vec![(Addr::unchecked("new"), Uint128::from(6u128))]
+        );
+        let a_poll = poll_read(&deps.storage).load(&2u64.to_be_bytes()).unwrap();
+        assert_eq!(
+            a_poll.commitments,
+            vec![(Addr::unchecked("new"), commitment)]
+        );
+
+        // the new address can use both
+        let mut env = mock_env();
+        env.block.height = start;
+        let msg = ExecuteMsg::ActivateReservation {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+        };
+        let info = mock_info("new", &[]);
+        let _ = execute(deps.as_mut(), env, info, msg).unwrap();
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.yes_votes, Uint128::from(6u128));
+    }
+
+    #[test]
+    fn voting_open_follows_poll_window() {
+        let mut deps = mock_instantiate();
+        let height = mock_env().block.height;
+        let msg: ExecuteMsg = CreatePollMsg {
+            start_height: Some(height + 10),
+            end_height: Some(height + 20),
+            ..create_poll_params("test")
+        }
+        .into();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let voting_open = |deps: Deps, height: u64| -> bool {
+            let mut env = mock_env();
+            env.block.height = height;
+            let msg = QueryMsg::VotingOpen { poll_id: 1 };
+            let res: VotingOpenResponse = from_binary(&query(deps, env, msg).unwrap()).unwrap();
+            res.open
+        };
+
+        assert!(!voting_open(deps.as_ref(), height + 9));
+        assert!(voting_open(deps.as_ref(), height + 10));
+        assert!(voting_open(deps.as_ref(), height + 19));
+        assert!(!voting_open(deps.as_ref(), height + 20));
+    }
+
+    #[test]
+    fn create_poll_rejects_blank_description() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            create_poll_msg(&" ".repeat(64)),
+        )
+        .unwrap_err();
+        match err {
+            ContractError::DescriptionBlank {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn quorum_denominator_matches_basis() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 250);
+
+        let msg = QueryMsg::QuorumDenominator { poll_id: 1 };
+        let res: QuorumDenominatorResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.basis, "staked_tokens");
+        assert_eq!(res.denominator, Uint128::from(250u128));
+
+        let msg = QueryMsg::QuorumDenominator { poll_id: 2 };
+        let _ = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+    }
+
+    #[test]
+    fn dry_run_end_poll_matches_end_poll() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        stake(deps.as_mut(), "voter1", 10);
+        stake(deps.as_mut(), "voter2", 10);
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(20, VOTING_TOKEN));
+
+        let info = mock_info("voter1", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 8)).unwrap();
+        let info = mock_info("voter2", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "no", 3)).unwrap();
+
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS;
+        let msg = QueryMsg::DryRunEndPoll { poll_id: 1 };
+        let dry_run: PollResult =
+            from_binary(&query(deps.as_ref(), env.clone(), msg.clone()).unwrap()).unwrap();
+        assert!(dry_run.passed);
+        assert_eq!(dry_run.tallied_weight, Uint128::from(11u128));
+
+        // nothing was persisted by the dry run
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.status, PollStatus::InProgress);
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            ExecuteMsg::EndPoll { poll_id: 1 },
+        )
+        .unwrap();
+        let result: PollResult = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(result, dry_run);
+
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.status, dry_run.status);
+
+        // once ended there is nothing left to dry run
+        let _ = query(deps.as_ref(), env, msg).unwrap_err();
+    }
+
+    #[test]
+    fn create_poll_rejects_duplicate_open_description() {
+        let mut deps = mock_dependencies(&[]);
+        let msg = InstantiateMsg {
+            reject_duplicate_descriptions: Some(true),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("same")).unwrap();
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, create_poll_msg("same")).unwrap_err();
+        match err {
+            ContractError::DuplicateDescription {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // duplicates are allowed when the flag is off
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("same")).unwrap();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("same")).unwrap();
+    }
+
+    #[test]
+    fn active_poll_ids_follow_create_and_end() {
+        let mut deps = mock_instantiate();
+        for description in ["first", "second", "third"].iter() {
+            let info = mock_info(TEST_CREATOR, &[]);
+            let _ = execute(
+                deps.as_mut(),
+                mock_env(),
+                info,
+                create_poll_msg(description),
+            )
+            .unwrap();
+        }
+
+        let msg = QueryMsg::ActivePollIds { limit: None };
+        let res: ActivePollIdsResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg.clone()).unwrap()).unwrap();
+        assert_eq!(res.poll_ids, vec![1, 2, 3]);
+
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS;
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), env, info, ExecuteMsg::EndPoll { poll_id: 2 }).unwrap();
+
+        let res: ActivePollIdsResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.poll_ids, vec![1, 3]);
+
+        let msg = QueryMsg::ActivePollIds { limit: Some(1) };
+        let res: ActivePollIdsResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.poll_ids, vec![1]);
+    }
+
+    #[test]
+    fn cancel_poll_unlocks_tokens() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("mistake")).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 100);
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 100)).unwrap();
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::CancelPoll { poll_id: 1 },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::PollNotCreator { .. } => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::CancelPoll { poll_id: 1 },
+        )
+        .unwrap();
+        assert_eq!(res.attributes[0], attr("action", "cancel_poll"));
+
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.status, PollStatus::Cancelled);
+        let state = config_read(&deps.storage).load().unwrap();
+        assert!(active_poll_ids(&deps.storage).unwrap().is_empty());
+        assert_eq!(state.active_voting_weight, Uint128::zero());
+
+        // the whole stake can be withdrawn straight away
+        let info = mock_info(TEST_VOTER, &[]);
+        let msg = ExecuteMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(100u128)),
+        };
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS;
+        let info = mock_info(TEST_CREATOR, &[]);
+        let err =
+            execute(deps.as_mut(), env, info, ExecuteMsg::EndPoll { poll_id: 1 }).unwrap_err();
+        match err {
+            ContractError::PollCancelled {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn cancel_poll_fails_after_end_height() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("late")).unwrap();
+
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS;
+        let expire_height = env.block.height;
+        let info = mock_info(TEST_CREATOR, &[]);
+        let err = execute(
+            deps.as_mut(),
+            env,
+            info,
+            ExecuteMsg::CancelPoll { poll_id: 1 },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::PollVotingPeriodExpired { expire_height: h } => {
+                assert_eq!(h, expire_height)
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.status, PollStatus::InProgress);
+    }
+
+    #[test]
+    fn poll_summaries_are_paginated_in_order() {
+        let mut deps = mock_instantiate();
+        for description in ["first", "second", "third"].iter() {
+            let info = mock_info(TEST_CREATOR, &[]);
+            let _ = execute(
+                deps.as_mut(),
+                mock_env(),
+                info,
+                create_poll_msg(description),
+            )
+            .unwrap();
+        }
+        stake(deps.as_mut(), TEST_VOTER, 10);
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(3, "no", 4)).unwrap();
+
+        let msg = QueryMsg::PollSummaries {
+            start_after: None,
+            limit: Some(2),
+        };
+        let res: PollSummariesResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.polls.len(), 2);
+        assert_eq!(res.polls[0].poll_id, 1);
+        assert_eq!(res.polls[0].description, "first");
+        assert_eq!(res.polls[1].poll_id, 2);
+
+        let msg = QueryMsg::PollSummaries {
+            start_after: Some(2),
+            limit: None,
+        };
+        let res: PollSummariesResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.polls.len(), 1);
+        assert_eq!(
+            res.polls[0],
+            PollSummary {
+                poll_id: 3,
+                description: "third".to_string(),
+                status: PollStatus::InProgress,
+                yes_votes: Uint128::zero(),
+                no_votes: Uint128::from(4u128),
+                end_height: mock_env().block.height + DEFAULT_END_HEIGHT_BLOCKS,
+            }
+        );
+
+        let msg = QueryMsg::PollSummaries {
+            start_after: Some(u64::MAX),
+            limit: None,
+        };
+        let res: PollSummariesResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert!(res.polls.is_empty());
+    }
+
+    #[test]
+    fn active_voting_weight_sums_open_polls() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), "voter1", 10);
+        stake(deps.as_mut(), "voter2", 10);
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(20, VOTING_TOKEN));
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("first")).unwrap();
+        let info = mock_info("voter1", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 4)).unwrap();
+        let info = mock_info("voter2", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "no", 2)).unwrap();
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("second")).unwrap();
+        let info = mock_info("voter1", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(2, "yes", 5)).unwrap();
+
+        let msg = QueryMsg::ActiveVotingWeight {};
+        let res: ActiveVotingWeightResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg.clone()).unwrap()).unwrap();
+        assert_eq!(res.total, Uint128::from(11u128));
+
+        // ending a poll removes its weight
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS;
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), env, info, ExecuteMsg::EndPoll { poll_id: 1 }).unwrap();
+
+        let res: ActiveVotingWeightResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.total, Uint128::from(5u128));
+    }
+
+    #[test]
+    fn create_poll_rejects_end_height_past_cap() {
+        let mut deps = mock_dependencies(&[]);
+        let max_end_height = mock_env().block.height + 1000;
+        let msg = InstantiateMsg {
+            max_absolute_end_height: Some(max_end_height),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg: ExecuteMsg = CreatePollMsg {
+            end_height: Some(max_end_height + 1),
+            ..create_poll_params("test")
+        }
+        .into();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::PollEndsTooLate {
+                max_end_height: max,
+            } => {
+                assert_eq!(max, max_end_height)
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // the default end height is past the cap too
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap_err();
+
+        let msg: ExecuteMsg = CreatePollMsg {
+            end_height: Some(max_end_height),
+            ..create_poll_params("test")
+        }
+        .into();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    }
+
+    #[test]
+    fn free_voting_power_subtracts_open_locks() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 150);
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 100)).unwrap();
+
+        let msg = QueryMsg::FreeVotingPower {
+            address: TEST_VOTER.to_string(),
+        };
+        let res: FreeVotingPowerResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.free, Uint128::from(50u128));
+    }
+
+    #[test]
+    fn update_config_applies_only_given_fields() {
+        let mut deps = mock_instantiate();
+        let before = config_read(&deps.storage).load().unwrap();
+
+        let msg = ExecuteMsg::UpdateConfig {
+            default_poll_duration: Some(500),
+            reject_duplicate_descriptions: Some(true),
+            max_absolute_end_height: Some(1_000_000),
+            clear_max_absolute_end_height: None,
+            denom: None,
+            min_stake_amount: Some(Uint128::from(10u128)),
+            min_desc_length: None,
+            max_desc_length: Some(128),
+        };
+
+        // only the owner can update
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.default_poll_duration, 500);
+        assert!(state.reject_duplicate_descriptions);
+        assert_eq!(state.max_absolute_end_height, Some(1_000_000));
+        assert_eq!(state.min_stake_amount, Uint128::from(10u128));
+        assert_eq!(state.min_desc_length, before.min_desc_length);
+        assert_eq!(state.max_desc_length, 128);
+        assert_eq!(state.denom, before.denom);
+        assert_eq!(state.owner, before.owner);
+        assert_eq!(state.creation_paused, before.creation_paused);
+
+        // a later partial update leaves the rest alone
+        let msg = ExecuteMsg::UpdateConfig {
+            default_poll_duration: None,
+            reject_duplicate_descriptions: Some(false),
+            max_absolute_end_height: None,
+            clear_max_absolute_end_height: None,
+            denom: None,
+            min_stake_amount: None,
+            min_desc_length: None,
+            max_desc_length: None,
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.default_poll_duration, 500);
+        assert!(!state.reject_duplicate_descriptions);
+        assert_eq!(state.max_absolute_end_height, Some(1_000_000));
+
+        // new polls use the updated default duration
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.end_height, mock_env().block.height + 500);
+
+        // the cap can be removed again
+        let msg = ExecuteMsg::UpdateConfig {
+            default_poll_duration: None,
+            reject_duplicate_descriptions: None,
+            max_absolute_end_height: None,
+            clear_max_absolute_end_height: Some(true),
+            denom: None,
+            min_stake_amount: None,
+            min_desc_length: None,
+            max_desc_length: None,
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.max_absolute_end_height, None);
+
+        // a zero default duration would give polls no blocks to vote in
+        let msg = ExecuteMsg::UpdateConfig {
+            default_poll_duration: Some(0),
+            reject_duplicate_descriptions: None,
+            max_absolute_end_height: None,
+            clear_max_absolute_end_height: None,
+            denom: None,
+            min_stake_amount: None,
+            min_desc_length: None,
+            max_desc_length: None,
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+        match err {
+            ContractError::InvalidPollDuration {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn update_config_rejects_denom_change_while_staked() {
+        let mut deps = mock_instantiate();
+        let msg = ExecuteMsg::UpdateConfig {
+            default_poll_duration: None,
+            reject_duplicate_descriptions: None,
+            max_absolute_end_height: None,
+            clear_max_absolute_end_height: None,
+            denom: Some("other_token".to_string()),
+            min_stake_amount: None,
+            min_desc_length: None,
+            max_desc_length: None,
+        };
+
+        stake(deps.as_mut(), TEST_VOTER, 10);
+        let info = mock_info(TEST_CREATOR, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+        match err {
+            ContractError::DenomChangeWithStake {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // once everything is withdrawn the denom can change
+        let info = mock_info(TEST_VOTER, &[]);
+        let withdraw = ExecuteMsg::WithdrawVotingTokens { amount: None };
+        let _ = execute(deps.as_mut(), mock_env(), info, withdraw).unwrap();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.denom, "other_token");
+    }
+
+    fn quorum_poll_msg(quorum_percentage: u8) -> ExecuteMsg {
+        CreatePollMsg {
+            quorum_percentage: Some(quorum_percentage),
+            ..create_poll_params("test")
+        }
+        .into()
+    }
+
+    fn end_poll_result(deps: DepsMut, poll_id: u64) -> PollResult {
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS;
+        let info = mock_info(TEST_CREATOR, &[]);
+        let res = execute(deps, env, info, ExecuteMsg::EndPoll { poll_id }).unwrap();
+        from_binary(&res.data.unwrap()).unwrap()
+    }
+
+    #[test]
+    fn quorum_boundary_is_inclusive() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), TEST_VOTER, 1000);
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(1000, VOTING_TOKEN));
+
+        // exactly 30% of the stake meets a 30% quorum
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, quorum_poll_msg(30)).unwrap();
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 300)).unwrap();
+
+        let result = end_poll_result(deps.as_mut(), 1);
+        assert_eq!(result.quorum, 30);
+        assert!(result.passed);
+        assert_eq!(result.rejected_reason, None);
+
+        // one token below is not enough
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, quorum_poll_msg(30)).unwrap();
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(2, "yes", 299)).unwrap();
+
+        let result = end_poll_result(deps.as_mut(), 2);
+        assert_eq!(result.quorum, 29);
+        assert!(!result.passed);
+        assert_eq!(result.rejected_reason, Some(RejectReason::QuorumNotReached));
+    }
+
+    #[test]
+    fn participation_rate_of_staked_tokens() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+
+        // no stake yet, so no participation
+        let msg = QueryMsg::ParticipationRate { poll_id: 1 };
+        let res: ParticipationRateResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg.clone()).unwrap()).unwrap();
+        assert_eq!(res.participation_percentage, 0);
+
+        stake(deps.as_mut(), "voter1", 100);
+        stake(deps.as_mut(), "voter2", 300);
+        let info = mock_info("voter1", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 100)).unwrap();
+        let info = mock_info("voter2", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "no", 50)).unwrap();
+
+        let res: ParticipationRateResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.participation_percentage, 37);
+        assert_eq!(res.tallied_weight, Uint128::from(150u128));
+        assert_eq!(res.staked_tokens, Uint128::from(400u128));
+    }
+
+    fn sub_poll_msg(description: &str, parent_poll_id: u64) -> ExecuteMsg {
+        CreatePollMsg {
+            parent_poll_id: Some(parent_poll_id),
+            ..create_poll_params(description)
+        }
+        .into()
+    }
+
+    #[test]
+    fn sub_polls_are_linked_to_parent() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("parent")).unwrap();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, sub_poll_msg("first", 1)).unwrap();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("other")).unwrap();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, sub_poll_msg("second", 1)).unwrap();
+
+        // the parent must exist
+        let info = mock_info(TEST_CREATOR, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, sub_poll_msg("orphan", 9)).unwrap_err();
+        match err {
+            ContractError::PollNotExist {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let msg = QueryMsg::SubPolls { parent_poll_id: 1 };
+        let res: SubPollsResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        let ids: Vec<u64> = res.polls.iter().map(|p| p.poll_id).collect();
+        assert_eq!(ids, vec![2, 4]);
+
+        // no amendments once the parent has ended
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS;
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), env, info, ExecuteMsg::EndPoll { poll_id: 1 }).unwrap();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, sub_poll_msg("late", 1)).unwrap_err();
+        match err {
+            ContractError::PollNotInProgress {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn expired_poll_stops_locking_before_end_poll() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 10);
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 6)).unwrap();
+
+        // past the end height the lock is gone even though nobody ended the poll
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS;
+        let msg = ExecuteMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(10u128)),
+        };
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // and the poll no longer takes votes
+        stake(deps.as_mut(), "voter2", 10);
+        let info = mock_info("voter2", &[]);
+        let err = execute(deps.as_mut(), env, info, vote_msg(1, "no", 5)).unwrap_err();
+        match err {
+            ContractError::PollVotingPeriodExpired { expire_height } => {
+                assert_eq!(
+                    expire_height,
+                    mock_env().block.height + DEFAULT_END_HEIGHT_BLOCKS
+                )
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn top_stakers_sorted_by_balance() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), "alice", 30);
+        stake(deps.as_mut(), "bob", 50);
+        stake(deps.as_mut(), "carol", 10);
+        stake(deps.as_mut(), "dave", 30);
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        let info = mock_info("bob", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 5)).unwrap();
+
+        let msg = QueryMsg::TopStakers { limit: Some(3) };
+        let res: TopStakersResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        let top: Vec<(&str, u128)> = res
+            .stakers
+            .iter()
+            .map(|s| (s.address.as_str(), s.token_balance.u128()))
+            .collect();
+        // equal balances are ordered by address
+        assert_eq!(top, vec![("bob", 50), ("alice", 30), ("dave", 30)]);
+        assert_eq!(res.stakers[0].polls_voted, 1);
+        assert_eq!(res.stakers[1].polls_voted, 0);
+    }
+
+    #[test]
+    fn stale_entries_hold_nothing() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), "alice", 30);
+        stake(deps.as_mut(), "bob", 50);
+
+        // moving the whole balance away leaves an empty entry behind
+        let msg = ExecuteMsg::TransferStake {
+            to: "carol".to_string(),
+            amount: Uint128::from(30u128),
+        };
+        let info = mock_info("alice", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = QueryMsg::StaleEntries { limit: None };
+        let res: StaleEntriesResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.addresses, vec!["alice".to_string()]);
+    }
+
+    #[test]
+    fn weight_unit_scales_tally_but_locks_full_weight() {
+        let mut deps = mock_dependencies(&[]);
+        let msg = InstantiateMsg {
+            weight_unit: Some(Uint128::from(1_000_000u128)),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 5_000_000);
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            vote_msg(1, "yes", 3_500_000),
+        )
+        .unwrap();
+        assert_eq!(res.attributes[2], attr("weight", "3"));
+
+        // below one unit the vote would be dust
+        stake(deps.as_mut(), "voter2", 5_000_000);
+        let info = mock_info("voter2", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "no", 999_999)).unwrap_err();
+        match err {
+            ContractError::WeightBelowUnit { weight_unit } => {
+                assert_eq!(weight_unit, Uint128::from(1_000_000u128))
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // the whole submitted weight stays locked
+        let msg = QueryMsg::CanWithdraw {
+            address: TEST_VOTER.to_string(),
+            amount: Uint128::from(1_500_000u128),
+        };
+        let res: CanWithdrawResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert!(res.can_withdraw);
+        assert_eq!(res.max_withdrawable, Uint128::from(1_500_000u128));
+
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(10_000_000, VOTING_TOKEN));
+        let result = end_poll_result(deps.as_mut(), 1);
+        assert_eq!(result.yes_votes, Uint128::from(3u128));
+        assert_eq!(result.tallied_weight, Uint128::from(3u128));
+    }
+
+    #[test]
+    fn stake_below_configured_minimum_fails() {
+        let mut deps = mock_dependencies(&[]);
+        let msg = InstantiateMsg {
+            min_stake_amount: Some(Uint128::from(100u128)),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res: State =
+            from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+        assert_eq!(res.min_stake_amount, Uint128::from(100u128));
+
+        let info = mock_info(TEST_VOTER, &coins(50, VOTING_TOKEN));
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::StakeVotingTokens {},
+        )
+        .unwrap_err();
+        match err {
+            ContractError::InsufficientFundsSent {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        stake(deps.as_mut(), TEST_VOTER, 100);
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.staked_tokens, Uint128::from(100u128));
+    }
+
+    #[test]
+    fn stake_without_denom_fails_with_zero_minimum() {
+        let mut deps = mock_dependencies(&[]);
+        let msg = InstantiateMsg {
+            min_stake_amount: Some(Uint128::zero()),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        for funds in [vec![], coins(50, "other_token")].iter() {
+            let info = mock_info(TEST_VOTER, funds);
+            let err = execute(
+                deps.as_mut(),
+                mock_env(),
+                info,
+                ExecuteMsg::StakeVotingTokens {},
+            )
+            .unwrap_err();
+            match err {
+                ContractError::InsufficientFundsSent {} => {}
+                e => panic!("unexpected error: {}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn configured_max_desc_length_allows_long_description() {
+        let description = "a".repeat(400);
+
+        // too long for the default limit
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            create_poll_msg(&description),
+        )
+        .unwrap_err();
+        match err {
+            ContractError::DescriptionTooLong { max_desc_length } => {
+                assert_eq!(max_desc_length, DEFAULT_MAX_DESC_LENGTH)
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let mut deps = mock_dependencies(&[]);
+        let msg = InstantiateMsg {
+            max_desc_length: Some(500),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            create_poll_msg(&description),
+        )
+        .unwrap();
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            create_poll_msg(&"a".repeat(501)),
+        )
+        .unwrap_err();
+        match err {
+            ContractError::DescriptionTooLong { max_desc_length } => {
+                assert_eq!(max_desc_length, 500)
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn cast_vote_rejects_stake_locked_in_other_polls() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("poll a")).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 100);
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 100)).unwrap();
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("poll b")).unwrap();
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, vote_msg(2, "yes", 50)).unwrap_err();
+        match err {
+            ContractError::InsufficientFreeStake { free_stake } => {
+                assert_eq!(free_stake, Uint128::zero())
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn creator_record_counts_outcomes() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), TEST_VOTER, 10);
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(10, VOTING_TOKEN));
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("passes")).unwrap();
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 10)).unwrap();
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("no votes")).unwrap();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            create_poll_msg("still open"),
+        )
+        .unwrap();
+        let info = mock_info("someone", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("not mine")).unwrap();
+
+        assert!(end_poll_result(deps.as_mut(), 1).passed);
+        assert!(!end_poll_result(deps.as_mut(), 2).passed);
+
+        let msg = QueryMsg::CreatorRecord {
+            creator: TEST_CREATOR.to_string(),
+        };
+        let res: CreatorRecordResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(
+            res,
+            CreatorRecordResponse {
+                total_created: 3,
+                passed: 1,
+                rejected: 1,
+                cancelled: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn conditional_poll_rejected_when_dependency_fails() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), TEST_VOTER, 10);
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(10, VOTING_TOKEN));
+
+        // the dependency must exist
+        let msg: ExecuteMsg = CreatePollMsg {
+            requires_poll: Some(1),
+            ..create_poll_params("dependent")
+        }
+        .into();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+        match err {
+            ContractError::PollNotExist {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            create_poll_msg("dependency"),
+        )
+        .unwrap();
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "no", 10)).unwrap();
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        stake(deps.as_mut(), "voter2", 10);
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(20, VOTING_TOKEN));
+        let info = mock_info("voter2", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(2, "yes", 10)).unwrap();
+
+        assert!(!end_poll_result(deps.as_mut(), 1).passed);
+        let result = end_poll_result(deps.as_mut(), 2);
+        assert!(!result.passed);
+        assert_eq!(result.rejected_reason, Some(RejectReason::DependencyNotMet));
+    }
+
+    #[test]
+    fn closest_to_quorum_picks_smallest_shortfall() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), "bystander", 20);
+
+        // polls at 10, 40 and 30 out of the 50 needed, plus one without a quorum
+        for (poll_id, (voter, weight)) in [("voter1", 10), ("voter2", 40), ("voter3", 30)]
+            .iter()
+            .enumerate()
+        {
+            let info = mock_info(TEST_CREATOR, &[]);
+            let _ = execute(deps.as_mut(), mock_env(), info, quorum_poll_msg(50)).unwrap();
+            stake(deps.as_mut(), voter, *weight);
+            let info = mock_info(voter, &[]);
+            let msg = vote_msg(poll_id as u64 + 1, "yes", *weight);
+            let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        }
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            create_poll_msg("no quorum"),
+        )
+        .unwrap();
+
+        let msg = QueryMsg::ClosestToQuorum {};
+        let res: ClosestToQuorumResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.poll_id, Some(2));
+        assert_eq!(res.shortfall, Uint128::from(10u128));
+    }
+
+    #[test]
+    fn vote_weight_capped_by_self_stake_percentage() {
+        let mut deps = mock_instantiate();
+        let msg: ExecuteMsg = CreatePollMsg {
+            max_self_stake_percentage: Some(50),
+            ..create_poll_params("capped")
+        }
+        .into();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 1000);
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 501)).unwrap_err();
+        match err {
+            ContractError::VoteWeightTooHigh { max_weight } => {
+                assert_eq!(max_weight, Uint128::from(500u128))
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 500)).unwrap();
+    }
+
+    #[test]
+    fn quorum_not_reached_with_thirty_of_hundred_staked() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), TEST_VOTER, 100);
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, quorum_poll_msg(40)).unwrap();
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 30)).unwrap();
+
+        let result = end_poll_result(deps.as_mut(), 1);
+        assert_eq!(result.quorum, 30);
+        assert!(!result.passed);
+        assert_eq!(result.rejected_reason, Some(RejectReason::QuorumNotReached));
+    }
+
+    #[test]
+    fn min_vote_percentage_scales_with_total_stake() {
+        let min_vote_poll: ExecuteMsg = CreatePollMsg {
+            min_vote_percentage: Some(10),
+            ..create_poll_params("test")
+        }
+        .into();
+
+        // 20 of 400 staked is below the 10% floor
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), TEST_VOTER, 20);
+        stake(deps.as_mut(), "bystander", 380);
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, min_vote_poll.clone()).unwrap();
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 20)).unwrap_err();
+        match err {
+            ContractError::VoteWeightTooLow { min_weight } => {
+                assert_eq!(min_weight, Uint128::from(40u128))
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // while 20 of 100 staked is above it
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), TEST_VOTER, 20);
+        stake(deps.as_mut(), "bystander", 80);
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, min_vote_poll).unwrap();
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 20)).unwrap();
+    }
+
+    #[test]
+    fn near_quorum_poll_auto_extends_once() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), TEST_VOTER, 100);
+
+        let msg: ExecuteMsg = CreatePollMsg {
+            quorum_percentage: Some(30),
+            auto_extend: Some(AutoExtend {
+                threshold: 5,
+                blocks: 10,
+                max_extensions: 1,
+            }),
+            ..create_poll_params("test")
+        }
+        .into();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 27)).unwrap();
+
+        // the dry run agrees with end_poll that the poll stays open
+        let msg = QueryMsg::DryRunEndPoll { poll_id: 1 };
+        let result: PollResult =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(result.status, PollStatus::InProgress);
+        assert_eq!(result.rejected_reason, None);
+
+        // the extension counts from the poll's end height, not from when end_poll is called
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS + 5;
+        let extended_end = mock_env().block.height + DEFAULT_END_HEIGHT_BLOCKS + 10;
+        let info = mock_info(TEST_CREATOR, &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::EndPoll { poll_id: 1 }).unwrap();
+        assert_eq!(res.attributes[0], attr("action", "auto_extend"));
+        assert_eq!(res.attributes[2], attr("end_height", &extended_end));
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.status, PollStatus::InProgress);
+        assert_eq!(a_poll.extensions, 1);
+
+        // the single extension is used up, so the poll is finalized this time
+        let mut env = mock_env();
+        env.block.height = extended_end;
+        let info = mock_info(TEST_CREATOR, &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::EndPoll { poll_id: 1 }).unwrap();
+        assert_eq!(res.attributes[0], attr("action", "end_poll"));
+        let result: PollResult = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(result.rejected_reason, Some(RejectReason::QuorumNotReached));
+    }
+
+    #[test]
+    fn auto_extend_refused_once_the_stake_is_reused() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), TEST_VOTER, 50);
+        stake(deps.as_mut(), "bystander", 50);
+
+        let msg: ExecuteMsg = CreatePollMsg {
+            quorum_percentage: Some(55),
+            auto_extend: Some(AutoExtend {
+                threshold: 5,
+                blocks: 10,
+                max_extensions: 1,
+            }),
+            ..create_poll_params("test")
+        }
+        .into();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 50)).unwrap();
+
+        // the lock in the first poll lapsed, so the same stake votes again
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS;
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), env.clone(), info, create_poll_msg("second")).unwrap();
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), env.clone(), info, vote_msg(2, "yes", 50)).unwrap();
+
+        // extending the first poll would lock 100 against a balance of 50
+        let msg = QueryMsg::DryRunEndPoll { poll_id: 1 };
+        let result: PollResult =
+            from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(result.rejected_reason, Some(RejectReason::QuorumNotReached));
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::EndPoll { poll_id: 1 }).unwrap();
+        assert_eq!(res.attributes[0], attr("action", "end_poll"));
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.status, PollStatus::Rejected);
+        assert_eq!(a_poll.extensions, 0);
+    }
+
+    #[test]
+    fn auto_extend_stays_within_max_end_height() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), TEST_VOTER, 100);
+
+        let end_height = mock_env().block.height + DEFAULT_END_HEIGHT_BLOCKS;
+        let msg = ExecuteMsg::UpdateConfig {
+            default_poll_duration: None,
+            reject_duplicate_descriptions: None,
+            max_absolute_end_height: Some(end_height + 5),
+            clear_max_absolute_end_height: None,
+            denom: None,
+            min_stake_amount: None,
+            min_desc_length: None,
+            max_desc_length: None,
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg: ExecuteMsg = CreatePollMsg {
+            quorum_percentage: Some(30),
+            auto_extend: Some(AutoExtend {
+                threshold: 5,
+                blocks: 10,
+                max_extensions: 1,
+            }),
+            ..create_poll_params("test")
+        }
+        .into();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 27)).unwrap();
+
+        // extending would pass the cap, so the poll is finalized instead
+        let msg = QueryMsg::DryRunEndPoll { poll_id: 1 };
+        let result: PollResult =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(result.rejected_reason, Some(RejectReason::QuorumNotReached));
+
+        let mut env = mock_env();
+        env.block.height = end_height;
+        let info = mock_info(TEST_CREATOR, &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::EndPoll { poll_id: 1 }).unwrap();
+        assert_eq!(res.attributes[0], attr("action", "end_poll"));
+        let result: PollResult = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(result.rejected_reason, Some(RejectReason::QuorumNotReached));
+    }
+
+    #[test]
+    fn zero_tracked_stake_rejects_poll() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), TEST_VOTER, 10);
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(10, VOTING_TOKEN));
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, quorum_poll_msg(30)).unwrap();
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 10)).unwrap();
+
+        // the lock lapses at the end height, so the voter can leave before the poll is ended
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS;
+        let info = mock_info(TEST_VOTER, &[]);
+        let msg = ExecuteMsg::WithdrawVotingTokens { amount: None };
+        let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert!(state.staked_tokens.is_zero());
+
+        let msg = QueryMsg::DryRunEndPoll { poll_id: 1 };
+        let result: PollResult =
+            from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(result.rejected_reason, Some(RejectReason::QuorumNotReached));
+        let msg = QueryMsg::ParticipationRate { poll_id: 1 };
+        let res: ParticipationRateResponse =
+            from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(res.participation_percentage, 0);
+        let msg = QueryMsg::ClosestToQuorum {};
+        let _ = query(deps.as_ref(), env.clone(), msg).unwrap();
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::EndPoll { poll_id: 1 }).unwrap();
+        let result: PollResult = from_binary(&res.data.unwrap()).unwrap();
+        assert!(!result.passed);
+        assert!(!result.quorum_reached);
+        assert_eq!(result.rejected_reason, Some(RejectReason::QuorumNotReached));
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.status, PollStatus::Rejected);
+        let state = config_read(&deps.storage).load().unwrap();
+        assert!(active_poll_ids(&deps.storage).unwrap().is_empty());
+        assert_eq!(state.active_voting_weight, Uint128::zero());
+    }
+
+    #[test]
+    fn rejected_reason_is_stored_on_poll() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), TEST_VOTER, 100);
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, quorum_poll_msg(40)).unwrap();
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 10)).unwrap();
+
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS;
+        let info = mock_info(TEST_CREATOR, &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::EndPoll { poll_id: 1 }).unwrap();
+        // events keep the readable reason
+        assert_eq!(
+            res.attributes[2],
+            attr("rejected_reason", "Quorum not reached")
+        );
+        let result: PollResult = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(result.rejected_reason, Some(RejectReason::QuorumNotReached));
+
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.rejected_reason, Some(RejectReason::QuorumNotReached));
+    }
+
+    #[test]
+    fn quorum_ignores_tokens_sent_without_staking() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), TEST_VOTER, 100);
+        // tokens sent straight to the contract are not staked by anyone
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(1000, VOTING_TOKEN));
+
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, quorum_poll_msg(40)).unwrap();
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 60)).unwrap();
+
+        let result = end_poll_result(deps.as_mut(), 1);
+        assert_eq!(result.quorum, 60);
+        assert!(result.passed);
+    }
+
+    fn commit_reveal_poll_msg() -> ExecuteMsg {
+        CreatePollMsg {
+            commit_reveal: Some(true),
+            ..create_poll_params("sealed")
+        }
+        .into()
+    }
+
+    #[test]
+    fn commit_reveal_counts_only_revealed_votes() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, commit_reveal_poll_msg()).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 10);
+        stake(deps.as_mut(), "voter2", 10);
+
+        // plain votes are not accepted
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 10)).unwrap_err();
+        match err {
+            ContractError::PollRequiresCommitReveal {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        for (voter, vote) in [(TEST_VOTER, "yes"), ("voter2", "no")].iter() {
+            let msg = ExecuteMsg::CommitVote {
+                poll_id: 1,
+                commitment: vote_commitment(vote.parse().unwrap(), Uint128::from(10u128), "salt"),
+            };
+            let info = mock_info(voter, &[]);
+            let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        }
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.yes_votes, Uint128::zero());
+
+        let reveal = ExecuteMsg::RevealVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Uint128::from(10u128),
+            salt: "salt".to_string(),
+        };
+
+        // reveals only open once voting has closed
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, reveal.clone()).unwrap_err();
+        match err {
+            ContractError::PollVotingPeriodNotExpired { .. } => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS;
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), env.clone(), info, reveal).unwrap();
+
+        // the poll can't be ended before the reveal period is over
+        let reveal_end_height = env.block.height + REVEAL_PERIOD_BLOCKS;
+        let info = mock_info(TEST_CREATOR, &[]);
+        let err =
+            execute(deps.as_mut(), env, info, ExecuteMsg::EndPoll { poll_id: 1 }).unwrap_err();
+        match err {
+            ContractError::PollVotingPeriodNotExpired { expire_height } => {
+                assert_eq!(expire_height, reveal_end_height)
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // voter2 never reveals
+        let mut env = mock_env();
+        env.block.height = reveal_end_height;
+        let info = mock_info(TEST_CREATOR, &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::EndPoll { poll_id: 1 }).unwrap();
+        let result: PollResult = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(result.yes_votes, Uint128::from(10u128));
+        assert_eq!(result.no_votes, Uint128::zero());
+        assert!(result.passed);
+    }
+
+    #[test]
+    fn revealed_weight_stays_locked_until_reveal_end() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, commit_reveal_poll_msg()).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 100);
+
+        let commitment = vote_commitment(VoteOption::Yes, Uint128::from(100u128), "salt");
+        for voter in [TEST_VOTER, "voter2"].iter() {
+            let msg = ExecuteMsg::CommitVote {
+                poll_id: 1,
+                commitment: commitment.clone(),
+            };
+            let info = mock_info(voter, &[]);
+            let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        }
+        let reveal = ExecuteMsg::RevealVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Uint128::from(100u128),
+            salt: "salt".to_string(),
+        };
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS;
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), env.clone(), info, reveal.clone()).unwrap();
+
+        // the revealed weight can't be handed on to reveal it a second time
+        let msg = ExecuteMsg::TransferStake {
+            to: "voter2".to_string(),
+            amount: Uint128::from(100u128),
+        };
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        match err {
+            ContractError::InsufficientFreeBalance { free_balance } => {
+                assert_eq!(free_balance, Uint128::zero())
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        // and nothing can be revealed once the reveal period is over
+        let reveal_end_height = env.block.height + REVEAL_PERIOD_BLOCKS;
+        env.block.height = reveal_end_height;
+        let info = mock_info("voter2", &[]);
+        let err = execute(deps.as_mut(), env, info, reveal).unwrap_err();
+        match err {
+            ContractError::RevealPeriodExpired {
+                reveal_end_height: h,
+            } => {
+                assert_eq!(h, reveal_end_height)
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn commit_vote_fails_before_start_height() {
+        let mut deps = mock_instantiate();
+        let start_height = mock_env().block.height + 10;
+        let msg: ExecuteMsg = CreatePollMsg {
+            start_height: Some(start_height),
+            commit_reveal: Some(true),
+            ..create_poll_params("sealed")
+        }
+        .into();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 10);
+
+        let msg = ExecuteMsg::CommitVote {
+            poll_id: 1,
+            commitment: vote_commitment(VoteOption::Yes, Uint128::from(10u128), "salt"),
+        };
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, msg.clone()).unwrap_err();
+        match err {
+            ContractError::PoolVotingPeriodNotStarted { start_height: h } => {
+                assert_eq!(h, start_height)
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let mut env = mock_env();
+        env.block.height = start_height;
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), env, info, msg).unwrap();
+    }
+
+    #[test]
+    fn reveal_with_wrong_salt_fails() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, commit_reveal_poll_msg()).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 10);
+
+        let msg = ExecuteMsg::CommitVote {
+            poll_id: 1,
+            commitment: vote_commitment(VoteOption::Yes, Uint128::from(10u128), "salt"),
+        };
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = ExecuteMsg::RevealVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Uint128::from(10u128),
+            salt: "pepper".to_string(),
+        };
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS;
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        match err {
+            ContractError::RevealMismatch {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn cast_vote_on_earlier_polls() {
+        let mut deps = mock_instantiate();
+        for description in ["first", "second", "third"].iter() {
+            let info = mock_info(TEST_CREATOR, &[]);
+            let _ = execute(
+                deps.as_mut(),
+                mock_env(),
+                info,
+                create_poll_msg(description),
+            )
+            .unwrap();
+        }
+        stake(deps.as_mut(), TEST_VOTER, 10);
+        stake(deps.as_mut(), "voter2", 10);
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 10)).unwrap();
+        let info = mock_info("voter2", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(2, "no", 10)).unwrap();
+
+        let info = mock_info("voter2", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, vote_msg(4, "yes", 1)).unwrap_err();
+        match err {
+            ContractError::PollNotExist {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn accepted_denoms_match_staking_mode() {
+        let deps = mock_instantiate();
+        let msg = QueryMsg::AcceptedDenoms {};
+        let res: AcceptedDenomsResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.denoms, vec![VOTING_TOKEN.to_string()]);
+
+        let mut deps = mock_dependencies(&[]);
+        let msg = InstantiateMsg {
+            cw20_token: Some(String::from("voting_cw20")),
+            ..default_instantiate_msg()
+        };
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let msg = QueryMsg::AcceptedDenoms {};
+        let res: AcceptedDenomsResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.denoms, vec!["voting_cw20".to_string()]);
+    }
+
+    #[test]
+    fn abstain_counts_for_quorum_not_threshold() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), "bystander", 38);
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, quorum_poll_msg(50)).unwrap();
+
+        // 22 of 100 voted yes or no, the abstains lift participation to 62
+        for (voter, vote, weight) in [
+            ("voter1", "abstain", 40),
+            ("voter2", "yes", 10),
+            ("voter3", "no", 12),
+        ]
+        .iter()
+        {
+            stake(deps.as_mut(), voter, *weight);
+            let info = mock_info(voter, &[]);
+            let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, vote, *weight)).unwrap();
+        }
+
+        let result = end_poll_result(deps.as_mut(), 1);
+        assert_eq!(result.abstain_votes, Uint128::from(40u128));
+        assert_eq!(result.tallied_weight, Uint128::from(62u128));
+        assert_eq!(result.quorum, 62);
+        assert!(!result.passed);
+        assert_eq!(
+            result.rejected_reason,
+            Some(RejectReason::ThresholdNotReached)
+        );
+
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.abstain_votes, Uint128::from(40u128));
+    }
+
+    #[test]
+    fn even_split_ends_tied() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("split")).unwrap();
+        for (voter, vote) in [("voter1", "yes"), ("voter2", "no")].iter() {
+            stake(deps.as_mut(), voter, 25);
+            let info = mock_info(voter, &[]);
+            let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, vote, 25)).unwrap();
+        }
+
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS;
+        let info = mock_info(TEST_CREATOR, &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::EndPoll { poll_id: 1 }).unwrap();
+        assert_eq!(res.attributes[2], attr("rejected_reason", "Tied"));
+        let result: PollResult = from_binary(&res.data.unwrap()).unwrap();
+        assert!(!result.passed);
+        assert_eq!(result.status, PollStatus::Tied);
+        assert_eq!(result.rejected_reason, Some(RejectReason::Tied));
+
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.status, PollStatus::Tied);
+    }
+
+    #[test]
+    fn end_poll_attributes_match_stored_tallies() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), "bystander", 50);
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, quorum_poll_msg(30)).unwrap();
+        for (voter, vote, weight) in [("voter1", "yes", 30), ("voter2", "no", 20)].iter() {
+            stake(deps.as_mut(), voter, *weight);
+            let info = mock_info(voter, &[]);
+            let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, vote, *weight)).unwrap();
+        }
+
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS;
+        let info = mock_info(TEST_CREATOR, &[]);
+        let res = execute(deps.as_mut(), env, info, ExecuteMsg::EndPoll { poll_id: 1 }).unwrap();
+
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.yes_votes, Uint128::from(30u128));
+        assert_eq!(a_poll.no_votes, Uint128::from(20u128));
+        assert_eq!(
+            res.attributes[4..],
+            [
+                attr("yes_votes", a_poll.yes_votes),
+                attr("no_votes", a_poll.no_votes),
+                attr("tallied_weight", "50"),
+                attr("quorum_reached", "true"),
+                attr("staked_weight", "100"),
+            ]
+        );
+    }
+
+    #[test]
+    fn default_end_height_counts_from_start_height() {
+        let mut deps = mock_instantiate();
+        let start_height = mock_env().block.height + 100;
+        let msg: ExecuteMsg = CreatePollMsg {
+            start_height: Some(start_height),
+            ..create_poll_params("later")
+        }
+        .into();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.end_height, start_height + DEFAULT_END_HEIGHT_BLOCKS);
+    }
+
+    fn vote_validity(deps: Deps, env: Env, poll_id: u64, weight: u128) -> VoteValidityResponse {
+        let msg = QueryMsg::VoteValidity {
+            poll_id,
+            address: TEST_VOTER.to_string(),
+            weight: Uint128::from(weight),
+        };
+        from_binary(&query(deps, env, msg).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn vote_validity_reports_cast_vote_errors() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("first")).unwrap();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("second")).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 10);
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(10, VOTING_TOKEN));
+
+        let res = vote_validity(deps.as_ref(), mock_env(), 1, 10);
+        assert!(res.valid);
+        assert_eq!(res.reason, None);
+
+        let res = vote_validity(deps.as_ref(), mock_env(), 3, 10);
+        assert!(!res.valid);
+        assert_eq!(res.reason, Some(ContractError::PollNotExist {}.to_string()));
+
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS;
+        let res = vote_validity(deps.as_ref(), env, 1, 10);
+        let expire_height = mock_env().block.height + DEFAULT_END_HEIGHT_BLOCKS;
+        let expected = ContractError::PollVotingPeriodExpired { expire_height };
+        assert_eq!(res.reason, Some(expected.to_string()));
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 10)).unwrap();
+        let res = vote_validity(deps.as_ref(), mock_env(), 1, 1);
+        assert_eq!(
+            res.reason,
+            Some(ContractError::PollSenderVoted {}.to_string())
+        );
+
+        // the whole stake is locked in the first poll
+        let res = vote_validity(deps.as_ref(), mock_env(), 2, 1);
+        let expected = ContractError::InsufficientFreeStake {
+            free_stake: Uint128::zero(),
+        };
+        assert_eq!(res.reason, Some(expected.to_string()));
+
+        let mut env = mock_env();
+        env.block.height += DEFAULT_END_HEIGHT_BLOCKS;
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), env, info, ExecuteMsg::EndPoll { poll_id: 1 }).unwrap();
+        let res = vote_validity(deps.as_ref(), mock_env(), 1, 1);
+        assert_eq!(
+            res.reason,
+            Some(ContractError::PollNotInProgress {}.to_string())
+        );
+    }
+
+    #[test]
+    fn change_vote_replaces_option_weight_and_lock() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 10);
+
+        let change = |vote: VoteOption, weight: u128| ExecuteMsg::ChangeVote {
+            poll_id: 1,
+            vote,
+            weight: Uint128::from(weight),
+        };
+
+        // nothing to change yet
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, change(VoteOption::Yes, 5)).unwrap_err();
+        match err {
+            ContractError::PollSenderNotVoted {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "no", 10)).unwrap();
+
+        // the old lock is released, so the whole balance can be voted again
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, change(VoteOption::Yes, 8)).unwrap();
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let err =
+            execute(deps.as_mut(), mock_env(), info, change(VoteOption::Yes, 11)).unwrap_err();
+        match err {
+            ContractError::PollInsufficientStake {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.yes_votes, Uint128::from(8u128));
+        assert_eq!(a_poll.no_votes, Uint128::zero());
+        assert_eq!(a_poll.voters.len(), 1);
+        assert_eq!(a_poll.voters[0].1.vote, VoteOption::Yes);
+
+        let msg = QueryMsg::CanWithdraw {
+            address: TEST_VOTER.to_string(),
+            amount: Uint128::from(2u128),
+        };
+        let res: CanWithdrawResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert!(res.can_withdraw);
+        assert_eq!(res.max_withdrawable, Uint128::from(2u128));
+
+        // no changes once the poll has ended
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(10, VOTING_TOKEN));
+        assert!(end_poll_result(deps.as_mut(), 1).passed);
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, change(VoteOption::No, 1)).unwrap_err();
+        match err {
+            ContractError::PollNotInProgress {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn voter_records_are_keyed_by_address() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        stake(deps.as_mut(), "voter1", 10);
+        stake(deps.as_mut(), "voter2", 10);
+
+        let info = mock_info("voter1", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 4)).unwrap();
+        let info = mock_info("voter2", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "no", 6)).unwrap();
+
+        // changing one vote leaves the other record alone
+        let msg = ExecuteMsg::ChangeVote {
+            poll_id: 1,
+            vote: VoteOption::Abstain,
+            weight: Uint128::from(2u128),
+        };
+        let info = mock_info("voter2", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.voters.len(), 2);
+        let (addr, voter) = &a_poll.voters[0];
+        assert_eq!(addr.as_str(), "voter1");
+        assert_eq!(
+            (voter.vote, voter.weight),
+            (VoteOption::Yes, Uint128::from(4u128))
+        );
+        let (addr, voter) = &a_poll.voters[1];
+        assert_eq!(addr.as_str(), "voter2");
+        assert_eq!(
+            (voter.vote, voter.weight),
+            (VoteOption::Abstain, Uint128::from(2u128))
+        );
+    }
+
+    #[test]
+    fn query_voter_returns_vote_and_weight() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 50);
+        let msg = ExecuteMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+            weight: Uint128::from(50u128),
+            comment: Some("because".to_string()),
+        };
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = QueryMsg::Voter {
+            poll_id: 1,
+            address: TEST_VOTER.to_string(),
+        };
+        let res: VoterResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(
+            res,
+            VoterResponse {
+                vote: VoteOption::Yes,
+                weight: Uint128::from(50u128),
+                comment: Some("because".to_string()),
+            }
+        );
+
+        let msg = QueryMsg::Voter {
+            poll_id: 1,
+            address: "voter2".to_string(),
+        };
+        let _ = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+        let msg = QueryMsg::Voter {
+            poll_id: 2,
+            address: TEST_VOTER.to_string(),
+        };
+        let _ = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+    }
+
+    #[test]
+    fn poll_with_my_vote_includes_own_vote() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        stake(deps.as_mut(), TEST_VOTER, 50);
+        let msg = ExecuteMsg::CastVote {
+            poll_id: 1,
+            vote: VoteOption::No,
+            weight: Uint128::from(20u128),
+            comment: Some("too early".to_string()),
+        };
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = QueryMsg::PollWithMyVote {
+            poll_id: 1,
+            address: TEST_VOTER.to_string(),
+        };
+        let res: PollWithMyVoteResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.poll.description, "test");
+        assert_eq!(res.poll.creator, TEST_CREATOR);
+        assert_eq!(
+            res.my_vote,
+            Some(VoterResponse {
+                vote: VoteOption::No,
+                weight: Uint128::from(20u128),
+                comment: Some("too early".to_string()),
+            })
+        );
+
+        let msg = QueryMsg::PollWithMyVote {
+            poll_id: 1,
+            address: "voter2".to_string(),
+        };
+        let res: PollWithMyVoteResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.poll.description, "test");
+        assert_eq!(res.my_vote, None);
+    }
+
+    #[test]
+    fn query_poll_returns_tallies() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        stake(deps.as_mut(), "voter1", 70);
+        let info = mock_info("voter1", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 70)).unwrap();
+
+        // the running tally while the poll is open
+        let msg = QueryMsg::Poll { poll_id: 1 };
+        let res: PollResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg.clone()).unwrap()).unwrap();
+        assert_eq!(res.status, PollStatus::InProgress);
+        assert_eq!(res.yes_votes, Uint128::from(70u128));
+        assert_eq!(res.no_votes, Uint128::zero());
+        assert_eq!(res.num_voters, 1);
+
+        stake(deps.as_mut(), "voter2", 30);
+        let info = mock_info("voter2", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "no", 30)).unwrap();
+        let _ = end_poll_result(deps.as_mut(), 1);
+
+        let res: PollResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.status, PollStatus::Passed);
+        assert_eq!(res.yes_votes, Uint128::from(70u128));
+        assert_eq!(res.no_votes, Uint128::from(30u128));
+        assert_eq!(res.num_voters, 2);
+    }
+
+    #[test]
+    fn voter_alignment_sums_weight_across_polls() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), TEST_VOTER, 100);
+        for (poll_id, vote, weight) in [(1, "yes", 30), (2, "no", 20), (3, "abstain", 10)].iter() {
+            let info = mock_info(TEST_CREATOR, &[]);
+            let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+            let info = mock_info(TEST_VOTER, &[]);
+            let _ = execute(
+                deps.as_mut(),
+                mock_env(),
+                info,
+                vote_msg(*poll_id, vote, *weight),
+            )
+            .unwrap();
+        }
+
+        let msg = QueryMsg::VoterAlignment {
+            address: TEST_VOTER.to_string(),
+        };
+        let res: VoterAlignmentResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.total_yes_weight, Uint128::from(30u128));
+        assert_eq!(res.total_no_weight, Uint128::from(20u128));
+        assert_eq!(res.polls_voted, 3);
+    }
+
+    #[test]
+    fn weight_stats_of_poll_voters() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        for (voter, weight) in [
+            ("voter1", 10),
+            ("voter2", 40),
+            ("voter3", 20),
+            ("voter4", 90),
+        ]
+        .iter()
+        {
+            stake(deps.as_mut(), voter, *weight);
+            let info = mock_info(voter, &[]);
+            let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", *weight)).unwrap();
+        }
+
+        let msg = QueryMsg::WeightStats { poll_id: 1 };
+        let res: WeightStatsResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(res.min_weight, Uint128::from(10u128));
+        assert_eq!(res.max_weight, Uint128::from(90u128));
+        // the two middle weights are 20 and 40
+        assert_eq!(res.median_weight, Uint128::from(30u128));
+        assert_eq!(res.mean_weight, Uint128::from(40u128));
+        assert_eq!(res.voters_counted, 4);
+    }
+
+    #[test]
+    fn weight_distribution_buckets_voters() {
+        let mut deps = mock_instantiate();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("test")).unwrap();
+        for (voter, weight) in [
+            ("voter1", 5),
+            ("voter2", 10),
+            ("voter3", 40),
+            ("voter4", 60),
+            ("voter5", 500),
+        ]
+        .iter()
+        {
+            stake(deps.as_mut(), voter, *weight);
+            let info = mock_info(voter, &[]);
+            let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", *weight)).unwrap();
+        }
+
+        let msg = QueryMsg::WeightDistribution {
+            poll_id: 1,
+            buckets: vec![Uint128::from(10u128), Uint128::from(100u128)],
+        };
+        let res: WeightDistributionResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(
+            res.buckets,
+            vec![
+                WeightBucket {
+                    upper_bound: Some(Uint128::from(10u128)),
+                    voters: 2,
+                    total_weight: Uint128::from(15u128),
+                },
+                WeightBucket {
+                    upper_bound: Some(Uint128::from(100u128)),
+                    voters: 2,
+                    total_weight: Uint128::from(100u128),
+                },
+                WeightBucket {
+                    upper_bound: None,
+                    voters: 1,
+                    total_weight: Uint128::from(500u128),
+                },
+            ]
+        );
+
+        let msg = QueryMsg::WeightDistribution {
+            poll_id: 1,
+            buckets: vec![Uint128::from(100u128), Uint128::from(10u128)],
+        };
+        let err = query(deps.as_ref(), mock_env(), msg).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("Buckets must be sorted ascending")
+        );
+    }
+
+    #[test]
+    fn cast_vote_rejected_before_start_height() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), TEST_VOTER, 10);
+        let start = mock_env().block.height + 10;
+        let msg: ExecuteMsg = CreatePollMsg {
+            start_height: Some(start),
+            end_height: Some(start + 50),
+            ..create_poll_params("scheduled")
+        }
+        .into();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.height = start - 1;
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), env, info, vote_msg(1, "yes", 10)).unwrap_err();
+        match err {
+            ContractError::PoolVotingPeriodNotStarted { start_height } => {
+                assert_eq!(start_height, start)
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let mut env = mock_env();
+        env.block.height = start;
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), env, info, vote_msg(1, "yes", 10)).unwrap();
+    }
+
+    #[test]
+    fn poll_schedule_mid_window() {
+        let mut deps = mock_instantiate();
+        let start = mock_env().block.height + 10;
+        let msg: ExecuteMsg = CreatePollMsg {
+            start_height: Some(start),
+            end_height: Some(start + 50),
+            ..create_poll_params("scheduled")
+        }
+        .into();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.height = start + 20;
+        let msg = QueryMsg::PollSchedule { poll_id: 1 };
+        let res: PollScheduleResponse =
+            from_binary(&query(deps.as_ref(), env, msg).unwrap()).unwrap();
+        assert_eq!(
+            res,
+            PollScheduleResponse {
+                start_height: Some(start),
+                end_height: start + 50,
+                current_height: start + 20,
+                blocks_until_start: 0,
+                blocks_until_end: 30,
+            }
+        );
+    }
+
+    #[test]
+    fn polls_are_listed_in_pages() {
+        let mut deps = mock_instantiate();
+        for i in 1..=15 {
+            let info = mock_info(TEST_CREATOR, &[]);
+            let msg = create_poll_msg(&format!("poll {}", i));
+            let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        }
+
+        let msg = QueryMsg::Polls {
+            start_after: None,
+            limit: None,
+            status_filter: None,
+        };
+        let first: PollsResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(first.polls.len(), DEFAULT_LIMIT as usize);
+
+        let msg = QueryMsg::Polls {
+            start_after: Some(DEFAULT_LIMIT as u64),
+            limit: Some(100),
+            status_filter: None,
+        };
+        let second: PollsResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert_eq!(second.polls.len(), 5);
+
+        let descriptions: Vec<String> = first
+            .polls
+            .iter()
+            .chain(second.polls.iter())
+            .map(|p| p.description.clone())
+            .collect();
+        let expected: Vec<String> = (1..=15).map(|i| format!("poll {}", i)).collect();
+        assert_eq!(descriptions, expected);
+
+        let msg = QueryMsg::Polls {
+            start_after: Some(u64::MAX),
+            limit: None,
+            status_filter: None,
+        };
+        let last: PollsResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        assert!(last.polls.is_empty());
+    }
+
+    #[test]
+    fn polls_filtered_by_status() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), TEST_VOTER, 10);
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(10, VOTING_TOKEN));
+
+        // poll 1 passes, poll 2 is rejected without votes, poll 3 stays open
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("passed")).unwrap();
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 10)).unwrap();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("rejected")).unwrap();
+        let _ = end_poll_result(deps.as_mut(), 1);
+        let _ = end_poll_result(deps.as_mut(), 2);
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, create_poll_msg("open")).unwrap();
+
+        for (status, expected) in [
+            (PollStatus::Passed, "passed"),
+            (PollStatus::Rejected, "rejected"),
+            (PollStatus::InProgress, "open"),
+        ]
+        .iter()
+        {
+            let msg = QueryMsg::Polls {
+                start_after: None,
+                limit: None,
+                status_filter: Some(status.clone()),
+            };
+            let res: PollsResponse =
+                from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+            let descriptions: Vec<&str> =
+                res.polls.iter().map(|p| p.description.as_str()).collect();
+            assert_eq!(descriptions, vec![*expected]);
+        }
+    }
+
+    #[test]
+    fn cast_vote_rejects_weight_over_poll_cap() {
+        let mut deps = mock_instantiate();
+        let msg: ExecuteMsg = CreatePollMsg {
+            max_total_weight: Some(Uint128::from(10u128)),
+            ..create_poll_params("capped")
+        }
+        .into();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        stake(deps.as_mut(), "voter1", 10);
+        stake(deps.as_mut(), "voter2", 10);
+        stake(deps.as_mut(), "voter3", 10);
+
+        let info = mock_info("voter1", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 6)).unwrap();
+        let info = mock_info("voter2", &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "no", 4)).unwrap();
+
+        let info = mock_info("voter3", &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, vote_msg(1, "yes", 1)).unwrap_err();
+        match err {
+            ContractError::PollWeightCapReached { max_total_weight } => {
+                assert_eq!(max_total_weight, Uint128::from(10u128))
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn reserve_for_poll_then_activate() {
+        let mut deps = mock_instantiate();
+        stake(deps.as_mut(), TEST_VOTER, 100);
+        let start_height = mock_env().block.height + 10;
+        let msg: ExecuteMsg = CreatePollMsg {
+            start_height: Some(start_height),
+            ..create_poll_params("upcoming")
+        }
+        .into();
+        let info = mock_info(TEST_CREATOR, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let reserve_msg = ExecuteMsg::ReserveForPoll {
+            poll_id: 1,
+            weight: Uint128::from(60u128),
+        };
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), mock_env(), info, reserve_msg.clone()).unwrap();
+
+        // the reserved weight is locked right away
+        let info = mock_info(TEST_VOTER, &[]);
+        let msg = ExecuteMsg::WithdrawVotingTokens {
+            amount: Some(Uint128::from(50u128)),
+        };
+        let _ = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+
+        let activate_msg = ExecuteMsg::ActivateReservation {
+            poll_id: 1,
+            vote: VoteOption::Yes,
+        };
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), mock_env(), info, activate_msg.clone()).unwrap_err();
+        match err {
+            ContractError::PoolVotingPeriodNotStarted { start_height: h } => {
+                assert_eq!(h, start_height)
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let mut env = mock_env();
+        env.block.height = start_height;
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), env.clone(), info, reserve_msg).unwrap_err();
+        match err {
+            ContractError::PollAlreadyOpen {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let _ = execute(deps.as_mut(), env.clone(), info, activate_msg.clone()).unwrap();
+        let a_poll = poll_read(&deps.storage).load(&1u64.to_be_bytes()).unwrap();
+        assert_eq!(a_poll.yes_votes, Uint128::from(60u128));
+        assert!(a_poll.reservations.is_empty());
+        let token_manager = bank_read(&deps.storage)
+            .load(TEST_VOTER.as_bytes())
+            .unwrap();
+        assert_eq!(
+            token_manager.locked_tokens,
+            vec![(1u64, Uint128::from(60u128))]
+        );
+
+        let info = mock_info(TEST_VOTER, &[]);
+        let err = execute(deps.as_mut(), env, info, activate_msg).unwrap_err();
+        match err {
+            ContractError::NoReservation {} => {}
+            e => panic!("unexpected error: {}", e),
+        }
+    }
 }